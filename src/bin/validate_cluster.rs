@@ -9,7 +9,7 @@ use chord::utils;
 use chord::utils::types::HashPos;
 use utils::crypto;
 
-use crate::chord_proto::{Empty, NodeSummaryMsg, SuccessorListMsg, HashPosMsg};
+use crate::chord_proto::{Empty, FindSuccessorsRequest, NodeSummaryMsg, SuccessorListMsg, HashPosMsg};
 use crate::chord_proto::chord_client::ChordClient;
 
 pub mod chord_proto {
@@ -21,6 +21,7 @@ const DURATION: Duration = Duration::from_secs(20 as u64);
 #[tokio::main]
 async fn main() {
     let mut node_summaries: Vec<NodeSummaryMsg> = Vec::new();
+    let mut clients: Vec<ChordClient<Channel>> = Vec::new();
     {
         let mut args: Vec<String> = env::args().collect();
         if args.len() == 1 {
@@ -36,19 +37,18 @@ async fn main() {
                 .unwrap().get_ref().clone();
 
             node_summaries.push(summary);
+            clients.push(client);
         }
         // child_handles getting out of scope will shut down nodes due to .kill_on_drop(true)
     }
 
     node_summaries.sort_by(|a: &NodeSummaryMsg, b: &NodeSummaryMsg| {
-        foo(a.pos.clone().unwrap()).cmp(&foo(b.pos.clone().unwrap()))
+        hash_pos_from_msg(a.pos.clone().unwrap()).cmp(&hash_pos_from_msg(b.pos.clone().unwrap()))
     });
 
     let node_ids: Vec<HashPos> = node_summaries.iter()
         .map(|node_summary: &NodeSummaryMsg| {
-            // let bytes_b: [u8; std::mem::size_of::<HashPos>()] = node_summary.pos.unwrap().key.try_into().unwrap();
-            // HashPos::from_be_bytes(bytes_b)
-            foo(node_summary.pos.clone().unwrap())
+            hash_pos_from_msg(node_summary.pos.clone().unwrap())
         })
         .collect::<Vec<HashPos>>();
 
@@ -71,12 +71,19 @@ async fn main() {
             let node_key_pointed_to = crypto::hash(&finger.address.as_bytes());
             let actually_responsible_node_key = get_responsible_node_for_key(finger_key, &node_ids);
             let actually_responsible_node_address = get_node_address_for_key(&actually_responsible_node_key, &node_summaries);
-            if node_key_pointed_to.ne(&actually_responsible_node_key) {
+            // beyond the first fingers, proximity neighbor selection may choose any node within
+            // the finger's interval instead of the responsible node
+            let interval_end: HashPos = match fingers.get(j + 1) {
+                Some(next_finger) => next_finger.id.parse::<HashPos>().unwrap(),
+                None => hash_pos_from_msg(node_summaries[i].pos.clone().unwrap()),
+            };
+            let is_in_interval = j > 1 && crypto::is_between(node_key_pointed_to, finger_key, interval_end, false, true);
+            if node_key_pointed_to.ne(&actually_responsible_node_key) && !is_in_interval {
                 if is_valid {
                     eprintln!("-----");
                     is_valid = false;
                 }
-                eprintln!("Node ({}, {}): Wrong finger entry! ", foo(node_summaries[i].pos.clone().unwrap()), node_summaries[i].url);
+                eprintln!("Node ({}, {}): Wrong finger entry! ", hash_pos_from_msg(node_summaries[i].pos.clone().unwrap()), node_summaries[i].url);
                 eprintln!("{}-th Finger {} points to node ({}, {}) ", j, finger_key, node_key_pointed_to, &finger.address);
                 eprintln!("But node ({}, {}) is responsible for {}", actually_responsible_node_key, actually_responsible_node_address, finger_key);
                 eprintln!("-----");
@@ -84,14 +91,22 @@ async fn main() {
         }
     }
 
-    // validate predecessor list
+    // validate successor list, on rings smaller than the list every other node appears once and a
+    // single node is its own successor
     for (i, node_summary) in node_summaries.iter().enumerate() {
-        let successor_list = node_summary.successor_list.as_ref();
-        for (j, successor_according_to_list) in successor_list.unwrap().successors.iter().enumerate() {
+        let successors = &node_summary.successor_list.as_ref().unwrap().successors;
+        let max_successor_count = (node_summaries.len() - 1).max(1);
+        if successors.is_empty() || successors.len() > max_successor_count {
+            eprintln!("-----");
+            eprintln!("Node ({}, {}): Successor list has {} entries in a ring of {} nodes", hash_pos_from_msg(node_summaries[i].pos.clone().unwrap()), node_summaries[i].url, successors.len(), node_summaries.len());
+            eprintln!("-----");
+            is_valid = false;
+        }
+        for (j, successor_according_to_list) in successors.iter().enumerate() {
             let actual_successor_address = &node_summaries[(i + j + 1) % node_summaries.len()].url;
             if successor_according_to_list.address.ne(actual_successor_address) {
                 eprintln!("-----");
-                eprintln!("Node ({}, {}): Wrong successor list! ", foo(node_summaries[i].pos.clone().unwrap()), node_summaries[i].url);
+                eprintln!("Node ({}, {}): Wrong successor list! ", hash_pos_from_msg(node_summaries[i].pos.clone().unwrap()), node_summaries[i].url);
                 eprintln!("Actual successor address: {}, but was {}", actual_successor_address, successor_according_to_list.address);
                 eprintln!("-----");
                 is_valid = false;
@@ -99,6 +114,68 @@ async fn main() {
         }
     }
 
+    // validate predecessor list, the mirror of the successor list
+    for (i, node_summary) in node_summaries.iter().enumerate() {
+        let predecessors = &node_summary.predecessor_list.as_ref().unwrap().predecessors;
+        if predecessors.len() > node_summaries.len() - 1 {
+            eprintln!("-----");
+            eprintln!("Node ({}, {}): Predecessor list has {} entries in a ring of {} nodes", hash_pos_from_msg(node_summaries[i].pos.clone().unwrap()), node_summaries[i].url, predecessors.len(), node_summaries.len());
+            eprintln!("-----");
+            is_valid = false;
+        }
+        for (j, predecessor_according_to_list) in predecessors.iter().enumerate() {
+            let actual_predecessor_address = &node_summaries[(i + node_summaries.len() * (j + 1) - j - 1) % node_summaries.len()].url;
+            if predecessor_according_to_list.address.ne(actual_predecessor_address) {
+                eprintln!("-----");
+                eprintln!("Node ({}, {}): Wrong predecessor list! ", hash_pos_from_msg(node_summaries[i].pos.clone().unwrap()), node_summaries[i].url);
+                eprintln!("Actual predecessor address: {}, but was {}", actual_predecessor_address, predecessor_according_to_list.address);
+                eprintln!("-----");
+                is_valid = false;
+            }
+        }
+    }
+
+    // validate lookups and their hop counts, every node looks up the position of every node
+    let mut hop_counts: Vec<u32> = Vec::new();
+    for client in clients.iter_mut() {
+        for node_summary in node_summaries.iter() {
+            let response = client.find_successor(Request::new(node_summary.pos.clone().unwrap()))
+                .await
+                .unwrap().into_inner();
+            if response.address.ne(&node_summary.url) {
+                eprintln!("-----");
+                eprintln!("Lookup of node ({}, {}) returned {} via {:?}", hash_pos_from_msg(node_summary.pos.clone().unwrap()), node_summary.url, response.address, response.path);
+                eprintln!("-----");
+                is_valid = false;
+            }
+            hop_counts.push(response.hop_count);
+        }
+    }
+
+    // validate the owner lists, the successors of a node's position start with the node itself
+    for (i, node_summary) in node_summaries.iter().enumerate() {
+        let response = clients[0].find_successors(Request::new(FindSuccessorsRequest {
+            pos: node_summary.pos.clone(),
+            count: node_summaries.len() as u32,
+        })).await.unwrap().into_inner();
+        let actual_successors: Vec<(HashPos, &String)> = (0..node_summaries.len())
+            .map(|j| &node_summaries[(i + j) % node_summaries.len()])
+            .map(|successor| (hash_pos_from_msg(successor.pos.clone().unwrap()), &successor.url))
+            .collect();
+        let successors: Vec<(HashPos, &String)> = response.successors.iter()
+            .map(|successor| (HashPos::from_be_bytes(successor.id.clone().try_into().unwrap()), &successor.address))
+            .collect();
+        if successors.ne(&actual_successors) {
+            eprintln!("-----");
+            eprintln!("Successors of node ({}, {}) are {:?}, but should be {:?}", hash_pos_from_msg(node_summary.pos.clone().unwrap()), node_summary.url, successors, actual_successors);
+            eprintln!("-----");
+            is_valid = false;
+        }
+    }
+
+    let average_hops = hop_counts.iter().sum::<u32>() as f64 / hop_counts.len() as f64;
+    let max_hops = hop_counts.iter().max().copied().unwrap_or(0);
+    eprintln!("Lookup hops: average {:.2}, max {}, log2(N) = {:.2}", average_hops, max_hops, (node_summaries.len() as f64).log2());
 
     if is_valid {
         eprintln!("Looks good!")
@@ -116,7 +193,7 @@ fn get_responsible_node_for_key(key: HashPos, other_nodes: &Vec<HashPos>) -> Has
 
 fn get_node_address_for_key(key: &HashPos, node_summaries: &Vec<NodeSummaryMsg>) -> String {
     node_summaries.iter()
-        .find(|node_summary| foo(node_summary.pos.clone().unwrap()).eq(key))
+        .find(|node_summary| hash_pos_from_msg(node_summary.pos.clone().unwrap()).eq(key))
         .unwrap()
         .url
         .clone()
@@ -182,7 +259,8 @@ fn get_base_node_start_up_command(tcp_node_port: u16, grpc_node_port: u16, peer_
         .expect("failed to start process")
 }
 
-fn foo(pos_msg: HashPosMsg) -> HashPos {
-    let bytes_b: [u8; std::mem::size_of::<HashPos>()] = pos_msg.key.try_into().unwrap();
-    HashPos::from_be_bytes(bytes_b)
+fn hash_pos_from_msg(pos_msg: HashPosMsg) -> HashPos {
+    let bytes: [u8; std::mem::size_of::<HashPos>()] = pos_msg.key.try_into()
+        .expect("Node reported a position of a different width, are all nodes on the same version?");
+    HashPos::from_be_bytes(bytes)
 }
@@ -6,23 +6,31 @@ use actix_web::{App, get, HttpResponse, HttpServer, post, Responder, web};
 use log::{error, info};
 use tokio::net::TcpListener;
 use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 use tonic::transport::Server;
 
-use chord::utils::config::Config;
+use chord::utils;
+use chord::utils::config::{Bootstrap, Config};
+use chord::utils::types::Address;
 
 use crate::threads::chord::{ChordService, connect_with_retry};
 use crate::threads::chord::chord_proto::chord_server::ChordServer;
 use crate::threads::client_api::handle_client_connection;
+use crate::node::finger_table::FingerTable;
+use crate::node::metrics::LookupStats;
+use crate::node::quota_storage::QuotaStats;
+use crate::node::storage::KvStore;
+use crate::threads::expiration::{sweep_expired_periodically, SweepStats};
 use crate::threads::fix_fingers::fix_fingers_periodically;
 use crate::threads::health::check_predecessor_health_periodically;
-use crate::threads::setup::setup;
+use crate::threads::setup::{setup, SetupChannels, SetupContext};
 use crate::threads::shutdown_handoff::shutdown_handoff;
+use crate::threads::snapshot::snapshot_periodically;
 use crate::threads::stabilize::stabilize_periodically;
 use crate::threads::successor_list::check_successor_list_periodically;
 use crate::threads::web::index;
 
 mod node;
-mod utils;
 mod threads;
 
 #[allow(warnings, unused, unused_imports, unused_import_braces, re)]
@@ -42,41 +50,63 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .unwrap();
     let config_clone = config.clone();
 
-    let api_address = config.api_address;
-    let p2p_address = config.p2p_address;
-    let web_address = config.web_address;
-    let join_address_option = config.join_address;
-    let pow_difficulty = config.pow_difficulty;
-    let dev_mode = config.dev_mode;
+    let api_address = config.api_address.clone();
+    let p2p_address = config.p2p_address.clone();
+    let web_address = config.web_address.clone();
 
     let mut thread_handles = Vec::new();
+    let mut handoff_handles = Vec::new();
 
     // Most threads need the address to the local gRPC service. Each thread needs an own variable
     // with that address as it needs to be moved into the thread
-    let cloned_grpc_addr_1 = p2p_address.clone();
-    let cloned_grpc_addr_2 = p2p_address.clone();
     let cloned_grpc_addr_3 = p2p_address.clone();
-    let cloned_grpc_addr_4 = p2p_address.clone();
-    let cloned_grpc_addr_5 = p2p_address.clone();
-    let cloned_grpc_addr_6 = p2p_address.clone();
-    let cloned_grpc_addr_7 = p2p_address.clone();
-    let own_grpc_address_8 = p2p_address.clone();
     let own_grpc_address_9 = p2p_address.clone();
 
-    // tokio one-shot-channels used for communication between threads
-    let (tx1, rx_grpc_service) = oneshot::channel();
-    let (tx2, rx_shutdown_handoff) = oneshot::channel();
-    let (tx3, rx_check_predecessor) = oneshot::channel();
-    let (tx4, rx_successor_list) = oneshot::channel();
-    let (tx5, rx_web_interface) = oneshot::channel();
+    // statistics of the expiration sweeper, shared with the web interface
+    let sweep_stats_arc = Arc::new(Mutex::new(SweepStats::default()));
+
+    // usage of the storage quota by all virtual nodes, the quota applies to the whole process
+    let quota_stats_arc = Arc::new(Mutex::new(QuotaStats::default()));
+
+    // latency of the lookups handled by all virtual nodes, shared with the web interface
+    let lookup_stats_arc = Arc::new(Mutex::new(LookupStats::default()));
+
+    // the first virtual node uses the configured address and joins through the bootstrap peers,
+    // all further virtual nodes join the ring through the first one
+    let virtual_node_addresses = config.virtual_node_addresses();
+    let mut rx_web_interfaces = Vec::new();
+    let mut rx_sweepers = Vec::new();
+    for (i, virtual_node_address) in virtual_node_addresses.iter().enumerate() {
+        let (bootstrap, data_dir_option) = if i == 0 {
+            (config.bootstrap.clone(), config.data_dir.clone())
+        } else {
+            (
+                Bootstrap { peers: vec![p2p_address.clone()], ..Bootstrap::default() },
+                config.data_dir.as_ref().map(|data_dir| format!("{}/vnode{}", data_dir, i)),
+            )
+        };
+        let node_handles = spawn_node(
+            &config, virtual_node_address.clone(), bootstrap, data_dir_option, quota_stats_arc.clone(), lookup_stats_arc.clone(),
+        );
+        thread_handles.extend(node_handles.thread_handles);
+        handoff_handles.push(node_handles.handoff_handle);
+        rx_web_interfaces.push(node_handles.rx_web_interface);
+        rx_sweepers.push(node_handles.rx_sweeper);
+    }
 
+    let sweep_stats_arc_1 = sweep_stats_arc.clone();
+    thread_handles.push(tokio::spawn(async move {
+        sweep_expired_periodically(rx_sweepers, sweep_stats_arc_1)
+            .await
+    }));
 
-    // the main thread starts up all other threads and finally awaits them
 
+    // the process exits once all virtual nodes have handed off their data
     thread_handles.push(tokio::spawn(async move {
-        setup(join_address_option, &cloned_grpc_addr_1, tx1, tx2, tx3, tx4, tx5)
-            .await
-            .unwrap();
+        for handoff_handle in handoff_handles {
+            let _ = handoff_handle.await;
+        }
+        exit(0)
     }));
 
 
@@ -91,9 +121,103 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
     }));
 
+    // Setup for web interface
+
+
+    thread_handles.push(tokio::spawn(async move {
+        info!("Starting up web interface  thread on {}", web_address);
+        let mut finger_table_arcs = Vec::new();
+        for rx_web_interface in rx_web_interfaces {
+            finger_table_arcs.push(rx_web_interface.await.unwrap());
+        }
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(web::Data::new(finger_table_arcs.clone()))
+                .app_data(web::Data::new(config_clone.clone()))
+                .app_data(web::Data::new(own_grpc_address_9.clone()))
+                .app_data(web::Data::new(sweep_stats_arc.clone()))
+                .app_data(web::Data::new(quota_stats_arc.clone()))
+                .app_data(web::Data::new(lookup_stats_arc.clone()))
+                .service(index)
+        })
+            .bind(web_address)
+            .unwrap()
+            .run();
+        if let Err(e) = server.await {
+            error!("Web server error: {}", e);
+        }
+    }));
+
+    for handle in thread_handles {
+        handle.await?;
+    }
+
+    Ok(())
+}
+
+/// Handles of the threads of a single (virtual) node
+struct NodeHandles {
+    thread_handles: Vec<JoinHandle<()>>,
+    /// finishes once the node has handed off its data on shutdown
+    handoff_handle: JoinHandle<()>,
+    /// receives the node's finger table for the web interface
+    rx_web_interface: oneshot::Receiver<Arc<Mutex<FingerTable>>>,
+    /// receives the node's storage for the expiration sweeper of the process
+    rx_sweeper: oneshot::Receiver<Arc<Mutex<KvStore>>>,
+}
+
+/// Starts up all threads of a single (virtual) node listening on the given gRPC address
+fn spawn_node(config: &Config, grpc_address: Address, bootstrap: Bootstrap, data_dir_option: Option<String>,
+              quota_stats_arc: Arc<Mutex<QuotaStats>>, lookup_stats_arc: Arc<Mutex<LookupStats>>,
+) -> NodeHandles {
+    let service_config = config.clone();
+    let successor_list_size = config.successor_list_size;
+    let quota = config.quota.clone();
+    let virtual_node_addresses = config.virtual_node_addresses();
+
+    let mut thread_handles = Vec::new();
+
+    // Most threads need the address to the local gRPC service. Each thread needs an own variable
+    // with that address as it needs to be moved into the thread
+    let cloned_grpc_addr_1 = grpc_address.clone();
+    let cloned_grpc_addr_2 = grpc_address.clone();
+    let cloned_grpc_addr_4 = grpc_address.clone();
+    let cloned_grpc_addr_5 = grpc_address.clone();
+    let cloned_grpc_addr_6 = grpc_address.clone();
+    let cloned_grpc_addr_7 = grpc_address.clone();
+    let own_grpc_address_8 = grpc_address.clone();
+
+    // tokio one-shot-channels used for communication between threads
+    let (tx1, rx_grpc_service) = oneshot::channel();
+    let (tx2, rx_shutdown_handoff) = oneshot::channel();
+    let (tx3, rx_check_predecessor) = oneshot::channel();
+    let (tx4, rx_successor_list) = oneshot::channel();
+    let (tx5, rx_web_interface) = oneshot::channel();
+    let (tx6, rx_snapshot) = oneshot::channel();
+    let (tx7, rx_sweeper) = oneshot::channel();
+
+
+    // the setup thread creates the node's data structures and hands them to all other threads
+
+    thread_handles.push(tokio::spawn(async move {
+        let context = SetupContext { bootstrap, own_grpc_address: cloned_grpc_addr_1, data_dir_option, quota, quota_stats_arc };
+        let channels = SetupChannels {
+            tx_grpc_thread: tx1,
+            tx_handoff_thread: tx2,
+            tx_check_predecessor: tx3,
+            tx_successor_list: tx4,
+            tx_web_interface: tx5,
+            tx_snapshot: tx6,
+            tx_sweeper: tx7,
+        };
+        setup(context, channels)
+            .await
+            .unwrap();
+    }));
+
 
     thread_handles.push(tokio::spawn(async move {
-        let chord_service = ChordServer::new(ChordService::new(rx_grpc_service, &cloned_grpc_addr_2, pow_difficulty, dev_mode).await);
+        let chord_service = ChordServer::new(ChordService::new(rx_grpc_service, &cloned_grpc_addr_2, &service_config, lookup_stats_arc).await);
         info!("Starting up gRPC service on {}", cloned_grpc_addr_2);
 
         let reflection_service = tonic_reflection::server::Builder::configure()
@@ -109,10 +233,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
     }));
 
 
-    thread_handles.push(tokio::spawn(async move {
-        shutdown_handoff(own_grpc_address_8.clone(), rx_shutdown_handoff).await.unwrap();
-        exit(0)
-    }));
+    let handoff_handle = tokio::spawn(async move {
+        shutdown_handoff(own_grpc_address_8.clone(), virtual_node_addresses, rx_shutdown_handoff).await.unwrap();
+    });
 
 
     thread_handles.push(tokio::spawn(async move {
@@ -128,42 +251,20 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
 
     thread_handles.push(tokio::spawn(async move {
-        check_predecessor_health_periodically(cloned_grpc_addr_6, rx_check_predecessor)
+        check_predecessor_health_periodically(cloned_grpc_addr_6, successor_list_size, rx_check_predecessor)
             .await
     }));
 
 
     thread_handles.push(tokio::spawn(async move {
-        check_successor_list_periodically(cloned_grpc_addr_7, rx_successor_list)
+        check_successor_list_periodically(cloned_grpc_addr_7, successor_list_size, rx_successor_list)
             .await
     }));
 
-    // Setup for web interface
-
-
     thread_handles.push(tokio::spawn(async move {
-        info!("Starting up web interface  thread on {}", web_address);
-        let finger_table_arc = rx_web_interface.await.unwrap();
-        let server = HttpServer::new(move || {
-            App::new()
-                .app_data(web::Data::new(finger_table_arc.clone()))
-                .app_data(web::Data::new(config_clone.clone()))
-                .app_data(web::Data::new(own_grpc_address_9.clone()))
-                .service(index)
-        })
-            .bind(web_address)
-            .unwrap()
-            .run();
-        if let Err(e) = server.await {
-            error!("Web server error: {}", e);
-        }
+        snapshot_periodically(rx_snapshot)
+            .await
     }));
 
-    for handle in thread_handles {
-        handle.await?;
-    }
-
-    Ok(())
+    NodeHandles { thread_handles, handoff_handle, rx_web_interface, rx_sweeper }
 }
-
-
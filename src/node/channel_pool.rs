@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use log::debug;
+use tonic::transport::Channel;
+
+use crate::utils::constants::CHANNEL_IDLE_TIMEOUT_MILLIS;
+use crate::utils::types::Address;
+
+/// Open gRPC channels to other nodes, keyed by their address. A channel is a multiplexed HTTP/2
+/// connection and cheap to clone, so routing steps can reuse it instead of opening a new
+/// connection per call. Channels that were not used for the idle timeout are closed.
+#[derive(Debug)]
+pub struct ChannelPool {
+    channels: HashMap<Address, (Channel, Instant)>,
+    idle_timeout: Duration,
+}
+
+impl ChannelPool {
+    pub fn new(idle_timeout: Duration) -> Self {
+        ChannelPool { channels: HashMap::new(), idle_timeout }
+    }
+
+    /// returns the pooled channel to the given address, unless it has been idle for too long
+    pub fn get(&mut self, address: &Address) -> Option<Channel> {
+        self.expire_idle();
+        let (channel, last_used) = self.channels.get_mut(address)?;
+        *last_used = Instant::now();
+        Some(channel.clone())
+    }
+
+    pub fn insert(&mut self, address: &Address, channel: Channel) {
+        self.channels.insert(address.clone(), (channel, Instant::now()));
+    }
+
+    /// drops the channel to the given address, the next connect opens a new connection
+    pub fn evict(&mut self, address: &Address) {
+        if self.channels.remove(address).is_some() {
+            debug!("Evicted channel to {}", address);
+        }
+    }
+
+    fn expire_idle(&mut self) {
+        let idle_timeout = self.idle_timeout;
+        self.channels.retain(|_, (_, last_used)| last_used.elapsed() < idle_timeout);
+    }
+}
+
+/// channel pool shared by all connections of this process
+pub fn channel_pool() -> &'static Mutex<ChannelPool> {
+    static CHANNEL_POOL: OnceLock<Mutex<ChannelPool>> = OnceLock::new();
+    CHANNEL_POOL.get_or_init(|| Mutex::new(ChannelPool::new(Duration::from_millis(CHANNEL_IDLE_TIMEOUT_MILLIS))))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use tonic::transport::Endpoint;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_idle_and_evicted_channels_are_dropped() {
+        let address: Address = "127.0.0.1:5601".to_string();
+        let channel = Endpoint::from_static("http://127.0.0.1:5601").connect_lazy();
+
+        let mut pool = ChannelPool::new(Duration::from_secs(60));
+        assert!(pool.get(&address).is_none());
+        pool.insert(&address, channel.clone());
+        assert!(pool.get(&address).is_some());
+        pool.evict(&address);
+        assert!(pool.get(&address).is_none());
+
+        let mut pool = ChannelPool::new(Duration::ZERO);
+        pool.insert(&address, channel);
+        assert!(pool.get(&address).is_none());
+        assert!(pool.channels.is_empty());
+    }
+}
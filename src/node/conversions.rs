@@ -1,9 +1,13 @@
+use chord::utils::config;
+
 use crate::node::finger_entry::FingerEntry;
+use crate::node::finger_table::FingerTable;
+use crate::node::predecessor_list::PredecessorList;
 use crate::node::successor_list::SuccessorList;
-use crate::threads::chord::chord_proto::{AddressMsg, FingerEntryDebugMsg, FingerEntryMsg, HashPosMsg, PowTokenMsg, SuccessorListMsg};
+use crate::threads::chord::chord_proto::{AddressMsg, FindSuccessorResponse, FingerEntryDebugMsg, FingerEntryMsg, FingerTableMsg, HashPosMsg, KvPairMsg, LookupMode, PowTokenMsg, PredecessorListMsg, SuccessorListMsg};
 use crate::utils::crypto;
 use crate::utils::proof_of_work::PowToken;
-use crate::utils::types::{Address, HashPos};
+use crate::utils::types::{Address, HashPos, Key, KvEntry};
 
 
 /// This file contains conversion implementations, mostly from proto-generated message structs to application structs
@@ -54,6 +58,14 @@ impl Into<FingerEntryMsg> for &FingerEntry {
 }
 
 
+impl Into<FingerTableMsg> for FingerTable {
+    fn into(self) -> FingerTableMsg {
+        FingerTableMsg {
+            fingers: self.fingers.into_iter().map(|finger| finger.into()).collect(),
+        }
+    }
+}
+
 impl Into<FingerEntry> for FingerEntryMsg {
     fn into(self) -> FingerEntry {
         FingerEntry {
@@ -126,6 +138,12 @@ impl Into<Address> for &AddressMsg {
     }
 }
 
+impl Into<Address> for FindSuccessorResponse {
+    fn into(self) -> Address {
+        self.address
+    }
+}
+
 impl Into<HashPosMsg> for AddressMsg {
     fn into(self) -> HashPosMsg {
         HashPosMsg {
@@ -235,6 +253,24 @@ impl Into<SuccessorList> for SuccessorListMsg {
     }
 }
 
+impl Into<PredecessorListMsg> for PredecessorList {
+    fn into(self) -> PredecessorListMsg {
+        PredecessorListMsg {
+            own_address: Some(self.own_address.into()),
+            predecessors: self.predecessors.iter().map(|pred| pred.into()).collect(),
+        }
+    }
+}
+
+impl Into<PredecessorList> for PredecessorListMsg {
+    fn into(self) -> PredecessorList {
+        PredecessorList {
+            own_address: self.own_address.unwrap().into(),
+            predecessors: self.predecessors.iter().map(|pred| pred.into()).collect(),
+        }
+    }
+}
+
 impl Into<PowTokenMsg> for PowToken {
     fn into(self) -> PowTokenMsg {
         PowTokenMsg {
@@ -254,3 +290,46 @@ impl Into<PowToken> for PowTokenMsg {
         }
     }
 }
+
+impl Into<KvPairMsg> for (Key, KvEntry) {
+    fn into(self) -> KvPairMsg {
+        let (key, entry) = self;
+        KvPairMsg {
+            key: key.to_vec(),
+            value: entry.value,
+            expiration_date: entry.expiration_date,
+            replication: entry.replication,
+            timestamp: entry.timestamp,
+            tombstone: entry.tombstone,
+            version: entry.version,
+        }
+    }
+}
+
+impl Into<KvPairMsg> for (&Key, &KvEntry) {
+    fn into(self) -> KvPairMsg {
+        (*self.0, self.1.clone()).into()
+    }
+}
+
+impl Into<(Key, KvEntry)> for KvPairMsg {
+    fn into(self) -> (Key, KvEntry) {
+        (self.key.try_into().unwrap(), KvEntry {
+            value: self.value,
+            expiration_date: self.expiration_date,
+            replication: self.replication,
+            timestamp: self.timestamp,
+            tombstone: self.tombstone,
+            version: self.version,
+        })
+    }
+}
+
+impl Into<LookupMode> for config::LookupMode {
+    fn into(self) -> LookupMode {
+        match self {
+            config::LookupMode::Recursive => LookupMode::Recursive,
+            config::LookupMode::Iterative => LookupMode::Iterative,
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::io;
+use std::path::Path;
+
+use log::error;
+
+use crate::node::memory_storage::MemoryStorage;
+use crate::node::storage::{PutError, Storage};
+use crate::node::write_ahead_log::{LogRecord, WriteAheadLog};
+use crate::utils::types::{HashPos, Key, KvEntry};
+
+/// Storage engine that serves all reads from memory, but additionally writes every mutation to a
+/// write-ahead log in the data directory, so that a restarted node can restore its data.
+#[derive(Debug)]
+pub struct DiskStorage {
+    memory: MemoryStorage,
+    wal: WriteAheadLog,
+}
+
+impl DiskStorage {
+    /// opens the storage in the given directory and restores the data found in it
+    pub fn open(data_dir: &Path) -> io::Result<Self> {
+        let (wal, records) = WriteAheadLog::open(data_dir)?;
+        let mut memory = MemoryStorage::new();
+        for record in records {
+            match record {
+                LogRecord::Put(key, entry) => { memory.put(key, entry); }
+                LogRecord::Remove(key) => { memory.remove(&key); }
+            }
+        }
+        Ok(DiskStorage { memory, wal })
+    }
+
+    fn append_to_log(&mut self, record: LogRecord) {
+        if let Err(err) = self.wal.append(&record) {
+            error!("Failed to append to write-ahead log: {}", err);
+        }
+    }
+}
+
+impl Storage for DiskStorage {
+    fn get(&self, key: &Key) -> Option<KvEntry> {
+        self.memory.get(key)
+    }
+
+    fn put(&mut self, key: Key, entry: KvEntry) -> Option<KvEntry> {
+        self.append_to_log(LogRecord::Put(key, entry.clone()));
+        self.memory.put(key, entry)
+    }
+
+    /// only stores the entry once it has been appended to the write-ahead log
+    fn try_put(&mut self, key: Key, entry: KvEntry) -> Result<Option<KvEntry>, PutError> {
+        self.wal.append(&LogRecord::Put(key, entry.clone())).map_err(PutError::Io)?;
+        Ok(self.memory.put(key, entry))
+    }
+
+    fn remove(&mut self, key: &Key) -> Option<KvEntry> {
+        self.memory.get(key)?;
+        self.append_to_log(LogRecord::Remove(*key));
+        self.memory.remove(key)
+    }
+
+    fn range(&self, lower: HashPos, upper: HashPos) -> Vec<(Key, KvEntry)> {
+        self.memory.range(lower, upper)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item=(&Key, &KvEntry)> + '_> {
+        self.memory.iter()
+    }
+
+    fn len(&self) -> usize {
+        self.memory.len()
+    }
+
+    fn is_persistent(&self) -> bool {
+        true
+    }
+
+    /// writes the full store to a new snapshot and truncates the write-ahead log
+    fn snapshot(&mut self) -> io::Result<()> {
+        self.wal.write_snapshot(self.memory.iter())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(value: &[u8]) -> KvEntry {
+        KvEntry { value: value.to_vec(), expiration_date: 100, replication: 1, timestamp: 42, tombstone: false, version: 1 }
+    }
+
+    #[test]
+    fn test_restore_from_snapshot_and_log() {
+        let data_dir = std::env::temp_dir().join(format!("chord_disk_storage_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&data_dir);
+
+        {
+            let mut storage = DiskStorage::open(&data_dir).unwrap();
+            storage.put([1; 32], entry(b"one"));
+            storage.put([2; 32], entry(&[0xff, 0x00, 0xfe]));
+            storage.snapshot().unwrap();
+            storage.put([3; 32], entry(b"three"));
+            storage.remove(&[1; 32]);
+        }
+
+        let storage = DiskStorage::open(&data_dir).unwrap();
+        assert_eq!(storage.len(), 2);
+        assert!(storage.get(&[1; 32]).is_none());
+        assert_eq!(storage.get(&[2; 32]).unwrap().value, vec![0xff, 0x00, 0xfe]);
+        assert_eq!(storage.get(&[3; 32]).unwrap().value, b"three".to_vec());
+        assert_eq!(storage.get(&[3; 32]).unwrap().timestamp, 42);
+
+        std::fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::utils::constants::{HEARTBEAT_WINDOW_SIZE, MIN_HEARTBEAT_STD_DEV_MILLIS, PHI_THRESHOLD};
+use crate::utils::types::Address;
+
+/// Probes of a peer: the intervals between consecutive probes, answered or not, are the cadence
+/// at which heartbeats are expected from this peer. Far fingers, for example, are probed much
+/// less often than the direct neighbours.
+#[derive(Debug)]
+struct HeartbeatHistory {
+    last_probe: Instant,
+    last_heartbeat: Instant,
+    intervals: VecDeque<Duration>,
+}
+
+impl HeartbeatHistory {
+    fn new(now: Instant) -> Self {
+        HeartbeatHistory {
+            last_probe: now,
+            last_heartbeat: now,
+            intervals: VecDeque::new(),
+        }
+    }
+
+    fn record_probe(&mut self, now: Instant) {
+        self.intervals.push_back(now.duration_since(self.last_probe));
+        if self.intervals.len() > HEARTBEAT_WINDOW_SIZE {
+            self.intervals.pop_front();
+        }
+        self.last_probe = now;
+    }
+
+    fn mean_millis(&self) -> f64 {
+        self.intervals.iter().map(|interval| interval.as_secs_f64() * 1000.0).sum::<f64>() / self.intervals.len() as f64
+    }
+
+    fn std_dev_millis(&self) -> f64 {
+        let mean = self.mean_millis();
+        let variance = self.intervals.iter()
+            .map(|interval| (interval.as_secs_f64() * 1000.0 - mean).powi(2))
+            .sum::<f64>() / self.intervals.len() as f64;
+        // a strictly periodic probe would otherwise suspect the peer as soon as one probe is late
+        variance.sqrt().max(MIN_HEARTBEAT_STD_DEV_MILLIS as f64).max(mean / 4.0)
+    }
+}
+
+/// Phi accrual failure detector (Hayashibara et al.): instead of declaring a peer dead after a
+/// single failed call, the suspicion level phi grows with the time since the last heartbeat,
+/// relative to the distribution of previous inter-arrival times. phi = 1 means a 10% chance that
+/// the peer is still alive and will answer, phi = 2 a 1% chance, and so on.
+/// Peers are suspected once phi exceeds the threshold.
+#[derive(Debug)]
+pub struct FailureDetector {
+    histories: HashMap<Address, HeartbeatHistory>,
+    threshold: f64,
+}
+
+impl FailureDetector {
+    pub fn new(threshold: f64) -> Self {
+        FailureDetector { histories: HashMap::new(), threshold }
+    }
+
+    /// records a probe of the peer that was answered
+    pub fn heartbeat(&mut self, address: &Address) {
+        self.heartbeat_at(address, Instant::now());
+    }
+
+    /// records a probe of the peer that failed. A peer that has never answered is suspected once
+    /// a few probes in a row have failed.
+    pub fn missed_heartbeat(&mut self, address: &Address) {
+        self.missed_heartbeat_at(address, Instant::now());
+    }
+
+    pub fn is_available(&self, address: &Address) -> bool {
+        self.phi_at(address, Instant::now()) < self.threshold
+    }
+
+    /// Removes suspected nodes from a neighbour list received from another node. Failed nodes
+    /// would otherwise be passed back and forth between the lists of the remaining nodes.
+    pub fn retain_available(&self, addresses: &mut Vec<Address>) {
+        addresses.retain(|address| self.is_available(address));
+    }
+
+    fn heartbeat_at(&mut self, address: &Address, now: Instant) {
+        match self.histories.get_mut(address) {
+            Some(history) => {
+                history.record_probe(now);
+                history.last_heartbeat = now;
+            }
+            None => {
+                self.histories.insert(address.clone(), HeartbeatHistory::new(now));
+            }
+        }
+    }
+
+    fn missed_heartbeat_at(&mut self, address: &Address, now: Instant) {
+        self.histories.entry(address.clone())
+            .and_modify(|history| history.record_probe(now))
+            .or_insert_with(|| HeartbeatHistory::new(now));
+    }
+
+    /// suspicion level of the peer, 0 for untracked peers and peers whose probe cadence is not
+    /// known yet
+    fn phi_at(&self, address: &Address, now: Instant) -> f64 {
+        let history = match self.histories.get(address) {
+            Some(history) if !history.intervals.is_empty() => history,
+            _ => return 0.0
+        };
+        let elapsed = now.duration_since(history.last_heartbeat).as_secs_f64() * 1000.0;
+        // logistic approximation of the normal distribution's cumulative distribution function
+        let y = (elapsed - history.mean_millis()) / history.std_dev_millis();
+        let e = (-y * (1.5976 + 0.070566 * y * y)).exp();
+        if elapsed > history.mean_millis() {
+            -(e / (1.0 + e)).log10()
+        } else {
+            -(1.0 - 1.0 / (1.0 + e)).log10()
+        }
+    }
+}
+
+/// failure detector shared by all maintenance threads of this process
+pub fn failure_detector() -> &'static Mutex<FailureDetector> {
+    static FAILURE_DETECTOR: OnceLock<Mutex<FailureDetector>> = OnceLock::new();
+    FAILURE_DETECTOR.get_or_init(|| Mutex::new(FailureDetector::new(PHI_THRESHOLD)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::constants::HEALTH_SLEEP_MILLIS;
+    use super::*;
+
+    #[test]
+    fn test_phi_grows_without_heartbeats() {
+        let address: Address = "127.0.0.1:5601".to_string();
+        let mut failure_detector = FailureDetector::new(PHI_THRESHOLD);
+        let start = Instant::now();
+        assert_eq!(failure_detector.phi_at(&address, start), 0.0);
+
+        for i in 0..10 {
+            failure_detector.heartbeat_at(&address, start + Duration::from_millis(i * HEALTH_SLEEP_MILLIS));
+        }
+        let last_heartbeat = start + Duration::from_millis(9 * HEALTH_SLEEP_MILLIS);
+
+        let phi_on_time = failure_detector.phi_at(&address, last_heartbeat + Duration::from_millis(HEALTH_SLEEP_MILLIS));
+        let phi_late = failure_detector.phi_at(&address, last_heartbeat + Duration::from_millis(2 * HEALTH_SLEEP_MILLIS));
+        let phi_missing = failure_detector.phi_at(&address, last_heartbeat + Duration::from_millis(10 * HEALTH_SLEEP_MILLIS));
+        assert!(phi_on_time < 1.0);
+        assert!(phi_on_time < phi_late);
+        assert!(phi_late < phi_missing);
+        assert!(phi_missing > PHI_THRESHOLD);
+    }
+
+    #[test]
+    fn test_probe_cadence_is_learned_per_peer() {
+        let far_finger: Address = "127.0.0.1:5601".to_string();
+        let failed_peer: Address = "127.0.0.1:5602".to_string();
+        let mut failure_detector = FailureDetector::new(PHI_THRESHOLD);
+        let start = Instant::now();
+        let cadence = Duration::from_millis(12_800);
+
+        // a peer that is probed rarely is not suspected between two probes
+        for i in 0..5 {
+            failure_detector.heartbeat_at(&far_finger, start + cadence * i);
+        }
+        assert!(failure_detector.phi_at(&far_finger, start + cadence * 5) < PHI_THRESHOLD);
+
+        // a peer that has never answered is suspected after a few failed probes
+        for i in 0..4 {
+            failure_detector.missed_heartbeat_at(&failed_peer, start + Duration::from_millis(i * HEALTH_SLEEP_MILLIS));
+        }
+        assert!(failure_detector.phi_at(&failed_peer, start + Duration::from_millis(3 * HEALTH_SLEEP_MILLIS)) > PHI_THRESHOLD);
+
+        // and is available again once a probe is answered
+        failure_detector.heartbeat_at(&failed_peer, start + Duration::from_millis(4 * HEALTH_SLEEP_MILLIS));
+        assert!(failure_detector.phi_at(&failed_peer, start + Duration::from_millis(4 * HEALTH_SLEEP_MILLIS)) < PHI_THRESHOLD);
+    }
+}
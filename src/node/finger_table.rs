@@ -1,7 +1,7 @@
 use std::fmt::Debug;
 use serde::Serialize;
 
-use crate::utils::crypto::{HashRingKey};
+use crate::utils::crypto::{hash, HashRingKey};
 use crate::node::finger_entry::FingerEntry;
 use crate::utils::types::{Address, HashPos};
 
@@ -26,6 +26,37 @@ impl FingerTable {
         FingerTable { fingers }
     }
 
+    /// points every finger to the known node that most closely succeeds the finger's start.
+    /// On join, the nodes known from the successor's finger table give a starting point until
+    /// the fingers are looked up.
+    pub fn seed(&mut self, known_addresses: &[Address]) {
+        for finger in self.fingers.iter_mut() {
+            let closest_option = known_addresses.iter()
+                .min_by_key(|address| hash(address.as_bytes()).wrapping_sub(finger.key));
+            if let Some(closest) = closest_option {
+                finger.address = closest.clone();
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_picks_closest_succeeding_node() {
+        let own_address: Address = "127.0.0.1:5601".to_string();
+        let known_addresses: Vec<Address> = vec!["127.0.0.1:5602".to_string(), "127.0.0.1:5603".to_string()];
+        let mut finger_table = FingerTable::new(&hash(own_address.as_bytes()));
+        finger_table.seed(&known_addresses);
+
+        for finger in finger_table.fingers.iter() {
+            let distance = |address: &Address| hash(address.as_bytes()).wrapping_sub(finger.key);
+            assert!(known_addresses.iter().all(|address| distance(&finger.address) <= distance(address)));
+        }
+    }
 }
 
 
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+
+use crate::utils::crypto::{hash, is_between};
+use crate::utils::types::{Address, HashPos};
+
+/// Ring ranges with a known responsible node, used to skip the lookup for hot keys.
+/// A lookup of a position that resolves to node n proves that no node lies between the position
+/// and n, so n is responsible for the whole range [position, n]. Ranges of the same node are
+/// merged. Entries may become stale when nodes join or leave, the responsible node then rejects
+/// the request and the range has to be invalidated.
+#[derive(Debug, Default)]
+pub struct LookupCache {
+    /// lower bound of the range and address, keyed by the position of the responsible node
+    ranges: BTreeMap<HashPos, (HashPos, Address)>,
+}
+
+impl LookupCache {
+    pub fn get(&self, pos: &HashPos) -> Option<Address> {
+        let (node_pos, (lower, address)) = self.ranges.range(pos..).next()
+            .or_else(|| self.ranges.iter().next())?;
+        if is_between(*pos, *lower, *node_pos, false, false) {
+            Some(address.clone())
+        } else {
+            None
+        }
+    }
+
+    /// records that the node with the given address is responsible for the position
+    pub fn insert(&mut self, pos: &HashPos, address: &Address) {
+        let node_pos = hash(address.as_bytes());
+        let lower = match self.ranges.get(&node_pos) {
+            Some((lower, cached_address)) if cached_address.eq(address) && is_between(*pos, *lower, node_pos, false, false) => *lower,
+            _ => *pos
+        };
+        self.ranges.insert(node_pos, (lower, address.clone()));
+    }
+
+    pub fn invalidate(&mut self, address: &Address) {
+        self.ranges.remove(&hash(address.as_bytes()));
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+}
+
+/// lookup cache shared by the client interfaces of this process
+pub fn lookup_cache() -> &'static Mutex<LookupCache> {
+    static LOOKUP_CACHE: OnceLock<Mutex<LookupCache>> = OnceLock::new();
+    LOOKUP_CACHE.get_or_init(|| Mutex::new(LookupCache::default()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges_are_merged_and_invalidated() {
+        let address: Address = "127.0.0.1:5601".to_string();
+        let node_pos = hash(address.as_bytes());
+        let mut cache = LookupCache::default();
+        assert!(cache.get(&node_pos).is_none());
+
+        cache.insert(&node_pos.wrapping_sub(10), &address);
+        assert_eq!(cache.get(&node_pos.wrapping_sub(5)), Some(address.clone()));
+        assert!(cache.get(&node_pos.wrapping_sub(20)).is_none());
+        assert!(cache.get(&node_pos.wrapping_add(1)).is_none());
+
+        cache.insert(&node_pos.wrapping_sub(20), &address);
+        cache.insert(&node_pos.wrapping_sub(15), &address);
+        assert_eq!(cache.get(&node_pos.wrapping_sub(20)), Some(address.clone()));
+
+        cache.invalidate(&address);
+        assert!(cache.get(&node_pos.wrapping_sub(5)).is_none());
+    }
+}
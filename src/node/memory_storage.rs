@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use crate::node::storage::Storage;
+use crate::utils::crypto::{hash, is_between};
+use crate::utils::types::{HashPos, Key, KvEntry};
+
+/// Storage engine that keeps all pairs in a HashMap, the data is lost when the process dies
+#[derive(Default, Debug)]
+pub struct MemoryStorage {
+    entries: HashMap<Key, KvEntry>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        MemoryStorage::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, key: &Key) -> Option<KvEntry> {
+        self.entries.get(key).cloned()
+    }
+
+    fn put(&mut self, key: Key, entry: KvEntry) -> Option<KvEntry> {
+        self.entries.insert(key, entry)
+    }
+
+    fn remove(&mut self, key: &Key) -> Option<KvEntry> {
+        self.entries.remove(key)
+    }
+
+    fn range(&self, lower: HashPos, upper: HashPos) -> Vec<(Key, KvEntry)> {
+        self.entries.iter()
+            .filter(|(key, _)| is_between(hash(*key), lower, upper, false, false))
+            .map(|(key, entry)| (*key, entry.clone()))
+            .collect()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item=(&Key, &KvEntry)> + '_> {
+        Box::new(self.entries.iter())
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_wraps_around_ring() {
+        let mut storage = MemoryStorage::new();
+        let keys: Vec<Key> = (0..20u8).map(|i| [i; 32]).collect();
+        for key in keys.iter() {
+            storage.put(*key, KvEntry::default());
+        }
+        let pivot = hash(&keys[0]);
+
+        let upper_part = storage.range(pivot, HashPos::MAX);
+        let lower_part = storage.range(0, pivot - 1);
+        let wrapped = storage.range(pivot, pivot - 1);
+
+        assert_eq!(upper_part.len() + lower_part.len(), keys.len());
+        assert_eq!(wrapped.len(), keys.len());
+        assert!(upper_part.iter().any(|(key, _)| key.eq(&keys[0])));
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::utils::types::Address;
+
+/// Smoothed round trip times to other nodes, used for proximity neighbor selection.
+/// Like in TCP, every new sample is weighted with 1/8.
+#[derive(Debug, Default)]
+pub struct RttTable {
+    rtts: HashMap<Address, Duration>,
+}
+
+impl RttTable {
+    pub fn record(&mut self, address: &Address, sample: Duration) {
+        let rtt = match self.rtts.get(address) {
+            Some(rtt) => (*rtt * 7 + sample) / 8,
+            None => sample,
+        };
+        self.rtts.insert(address.clone(), rtt);
+    }
+
+    pub fn get(&self, address: &Address) -> Option<Duration> {
+        self.rtts.get(address).copied()
+    }
+}
+
+/// Latency of the lookups handled by this node, shown on the web interface
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LookupStats {
+    pub lookups: u64,
+    pub last_micros: u64,
+    pub max_micros: u64,
+    pub average_micros: u64,
+    pub average_hops: f64,
+    #[serde(skip_serializing)]
+    total_micros: u64,
+    #[serde(skip_serializing)]
+    total_hops: u64,
+}
+
+impl LookupStats {
+    pub fn record(&mut self, latency: Duration, hops: usize) {
+        let micros = latency.as_micros() as u64;
+        self.lookups += 1;
+        self.last_micros = micros;
+        self.max_micros = self.max_micros.max(micros);
+        self.total_micros += micros;
+        self.total_hops += hops as u64;
+        self.average_micros = self.total_micros / self.lookups;
+        self.average_hops = self.total_hops as f64 / self.lookups as f64;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtt_is_smoothed() {
+        let mut rtt_table = RttTable::default();
+        let address: Address = "127.0.0.1:5601".to_string();
+        assert!(rtt_table.get(&address).is_none());
+
+        rtt_table.record(&address, Duration::from_millis(80));
+        assert_eq!(rtt_table.get(&address), Some(Duration::from_millis(80)));
+
+        rtt_table.record(&address, Duration::from_millis(0));
+        assert_eq!(rtt_table.get(&address), Some(Duration::from_millis(70)));
+    }
+}
@@ -2,3 +2,14 @@ pub mod finger_table;
 pub mod finger_entry;
 pub mod conversions;
 pub mod successor_list;
+pub mod storage;
+pub mod memory_storage;
+pub mod disk_storage;
+pub mod write_ahead_log;
+pub mod quota_storage;
+pub mod metrics;
+pub mod channel_pool;
+pub mod lookup_cache;
+pub mod predecessor_list;
+pub mod failure_detector;
+pub mod transfer;
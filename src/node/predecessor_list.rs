@@ -0,0 +1,36 @@
+use crate::utils::types::Address;
+
+/// Mirror of the successor list: each node stores the urls of its nearest predecessors, the
+/// direct predecessor first. If the direct predecessor fails, the next reachable node of the list
+/// becomes the new predecessor and this node takes over the failed node's range, whose keys it
+/// already holds as replicas.
+
+#[derive(Default, Debug, Clone)]
+pub struct PredecessorList {
+    pub own_address: Address,
+    pub predecessors: Vec<Address>,
+}
+
+impl PredecessorList {
+    pub fn new(own_address: &Address) -> Self {
+        PredecessorList {
+            own_address: own_address.clone(),
+            predecessors: Vec::new(),
+        }
+    }
+
+    /// replaces the predecessors with the direct predecessor followed by its predecessors, this
+    /// node and duplicates are skipped where the list wraps around the ring
+    pub fn update_with_other_pred_list(&mut self, other_list: PredecessorList, size: usize) {
+        let mut predecessors: Vec<Address> = Vec::new();
+        for predecessor in std::iter::once(other_list.own_address).chain(other_list.predecessors) {
+            if predecessors.len() == size {
+                break;
+            }
+            if predecessor.ne(&self.own_address) && !predecessor.is_empty() && !predecessors.contains(&predecessor) {
+                predecessors.push(predecessor);
+            }
+        }
+        self.predecessors = predecessors;
+    }
+}
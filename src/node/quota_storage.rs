@@ -0,0 +1,323 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use log::{debug, warn};
+use serde::Serialize;
+
+use crate::node::storage::{KvStore, PutError, Storage};
+use chord::utils::config::{EvictionPolicy, StorageQuota};
+use crate::utils::types::{HashPos, Key, KvEntry};
+
+/// Usage of the storage quota by all virtual nodes of the process, shown on the web interface
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QuotaStats {
+    pub used_bytes: u64,
+    pub entries: usize,
+    pub evicted: u64,
+    pub rejected: u64,
+}
+
+/// Storage wrapper that bounds the number of entries and bytes stored by the wrapped engine.
+/// If a put does not fit, either the put is rejected or other entries are evicted, depending on
+/// the configured eviction policy. Evicted entries are only dropped on this node, replicas on
+/// other nodes are kept.
+/// The virtual nodes of a process share the quota: their storages account their usage in the
+/// same stats, but each of them only evicts its own entries.
+#[derive(Debug)]
+pub struct QuotaStorage {
+    inner: Box<KvStore>,
+    quota: StorageQuota,
+    /// logical clock of the accesses, used for LRU eviction
+    access_clock: Cell<u64>,
+    /// eviction rank of every key, the last access for LRU and the expiration date for earliest
+    /// expiry eviction
+    ranks: RefCell<HashMap<Key, u64>>,
+    /// keys ordered by their eviction rank, so that the next victim is found without a scan
+    eviction_order: RefCell<BTreeSet<(u64, Key)>>,
+    stats_arc: Arc<Mutex<QuotaStats>>,
+}
+
+/// bytes accounted for a pair, the key and the value
+fn entry_size(key: &Key, entry: &KvEntry) -> u64 {
+    (key.len() + entry.value.len()) as u64
+}
+
+impl QuotaStorage {
+    pub fn new(inner: Box<KvStore>, quota: StorageQuota, stats_arc: Arc<Mutex<QuotaStats>>) -> Self {
+        {
+            let mut stats = stats_arc.lock().unwrap();
+            stats.used_bytes += inner.iter().map(|(key, entry)| entry_size(key, entry)).sum::<u64>();
+            stats.entries += inner.len();
+        }
+        let quota_storage = QuotaStorage {
+            inner,
+            quota,
+            access_clock: Cell::new(0),
+            ranks: RefCell::new(HashMap::new()),
+            eviction_order: RefCell::new(BTreeSet::new()),
+            stats_arc,
+        };
+        // restored entries have not been accessed yet, so they are the first to be evicted by LRU
+        for (key, entry) in quota_storage.inner.iter().filter(|(_, entry)| !entry.tombstone) {
+            match quota_storage.quota.eviction_policy {
+                EvictionPolicy::Reject => {}
+                EvictionPolicy::Lru => quota_storage.set_rank(key, 0),
+                EvictionPolicy::EarliestExpiry => quota_storage.set_rank(key, entry.expiration_date),
+            }
+        }
+        quota_storage
+    }
+
+    fn set_rank(&self, key: &Key, rank: u64) {
+        let mut eviction_order = self.eviction_order.borrow_mut();
+        if let Some(previous_rank) = self.ranks.borrow_mut().insert(*key, rank) {
+            eviction_order.remove(&(previous_rank, *key));
+        }
+        eviction_order.insert((rank, *key));
+    }
+
+    fn remove_rank(&self, key: &Key) {
+        if let Some(rank) = self.ranks.borrow_mut().remove(key) {
+            self.eviction_order.borrow_mut().remove(&(rank, *key));
+        }
+    }
+
+    /// records an access to the key for LRU eviction
+    fn touch(&self, key: &Key) {
+        if let EvictionPolicy::Lru = self.quota.eviction_policy {
+            let time = self.access_clock.get() + 1;
+            self.access_clock.set(time);
+            self.set_rank(key, time);
+        }
+    }
+
+    fn fits(&self, bytes: u64, entries: usize) -> bool {
+        self.quota.max_bytes.is_none_or(|max_bytes| bytes <= max_bytes)
+            && self.quota.max_entries.is_none_or(|max_entries| entries <= max_entries)
+    }
+
+    /// selects the entry with the lowest eviction rank, never the given key. The index is empty
+    /// if the policy is to reject. Tombstones are not indexed and never evicted, as read repair
+    /// would otherwise copy the removed value back from another replica.
+    fn select_victim(&self, key: &Key) -> Option<Key> {
+        self.eviction_order.borrow().iter()
+            .map(|(_, other_key)| *other_key)
+            .find(|other_key| other_key != key)
+    }
+}
+
+impl Storage for QuotaStorage {
+    fn get(&self, key: &Key) -> Option<KvEntry> {
+        let entry_option = self.inner.get(key);
+        if entry_option.as_ref().is_some_and(|entry| !entry.tombstone) {
+            self.touch(key);
+        }
+        entry_option
+    }
+
+    fn put(&mut self, key: Key, entry: KvEntry) -> Option<KvEntry> {
+        match self.try_put(key, entry) {
+            Ok(previous_option) => previous_option,
+            Err(err) => {
+                warn!("Dropped key {:?}, {}", key, err);
+                None
+            }
+        }
+    }
+
+    fn try_put(&mut self, key: Key, entry: KvEntry) -> Result<Option<KvEntry>, PutError> {
+        let previous_size_option = self.inner.get(&key).map(|previous| entry_size(&key, &previous));
+        let is_new_key = previous_size_option.is_none();
+        let previous_size = previous_size_option.unwrap_or(0);
+        let new_size = entry_size(&key, &entry);
+
+        // an entry that does not even fit into an empty storage is rejected without evicting
+        if !self.fits(new_size, 1) {
+            self.stats_arc.lock().unwrap().rejected += 1;
+            return Err(PutError::QuotaExceeded);
+        }
+
+        // the usage is reserved before the entry is stored, so that the virtual nodes sharing the
+        // quota can not exceed it together
+        loop {
+            {
+                let mut stats = self.stats_arc.lock().unwrap();
+                let used_bytes = stats.used_bytes - previous_size + new_size;
+                let entries = stats.entries + is_new_key as usize;
+                if self.fits(used_bytes, entries) {
+                    stats.used_bytes = used_bytes;
+                    stats.entries = entries;
+                    break;
+                }
+            }
+            match self.select_victim(&key) {
+                Some(victim) => {
+                    self.remove(&victim);
+                    self.stats_arc.lock().unwrap().evicted += 1;
+                    debug!("Evicted key {:?} to make room for key {:?}", victim, key);
+                }
+                None => {
+                    self.stats_arc.lock().unwrap().rejected += 1;
+                    return Err(PutError::QuotaExceeded);
+                }
+            }
+        }
+
+        let expiration_date = entry.expiration_date;
+        let tombstone = entry.tombstone;
+        match self.inner.try_put(key, entry) {
+            Ok(previous_option) => {
+                match self.quota.eviction_policy {
+                    EvictionPolicy::Reject => {}
+                    _ if tombstone => self.remove_rank(&key),
+                    EvictionPolicy::Lru => self.touch(&key),
+                    EvictionPolicy::EarliestExpiry => self.set_rank(&key, expiration_date),
+                }
+                Ok(previous_option)
+            }
+            Err(err) => {
+                let mut stats = self.stats_arc.lock().unwrap();
+                stats.used_bytes = stats.used_bytes + previous_size - new_size;
+                stats.entries -= is_new_key as usize;
+                Err(err)
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &Key) -> Option<KvEntry> {
+        let removed_option = self.inner.remove(key);
+        if let Some(removed) = &removed_option {
+            self.remove_rank(key);
+            let mut stats = self.stats_arc.lock().unwrap();
+            stats.used_bytes -= entry_size(key, removed);
+            stats.entries -= 1;
+        }
+        removed_option
+    }
+
+    fn range(&self, lower: HashPos, upper: HashPos) -> Vec<(Key, KvEntry)> {
+        self.inner.range(lower, upper)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item=(&Key, &KvEntry)> + '_> {
+        self.inner.iter()
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn is_persistent(&self) -> bool {
+        self.inner.is_persistent()
+    }
+
+    fn snapshot(&mut self) -> io::Result<()> {
+        self.inner.snapshot()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::node::memory_storage::MemoryStorage;
+
+    use super::*;
+
+    fn quota_storage(max_entries: usize, eviction_policy: EvictionPolicy) -> QuotaStorage {
+        let quota = StorageQuota { max_bytes: None, max_entries: Some(max_entries), eviction_policy };
+        QuotaStorage::new(Box::new(MemoryStorage::new()), quota, Arc::new(Mutex::new(QuotaStats::default())))
+    }
+
+    fn entry(expiration_date: u64) -> KvEntry {
+        KvEntry { value: b"value".to_vec(), expiration_date, ..KvEntry::default() }
+    }
+
+    #[test]
+    fn test_eviction_policies() {
+        let mut storage = quota_storage(2, EvictionPolicy::Reject);
+        storage.try_put([1; 32], entry(10)).unwrap();
+        storage.try_put([2; 32], entry(20)).unwrap();
+        assert!(storage.try_put([1; 32], entry(30)).is_ok());
+        assert!(storage.try_put([3; 32], entry(30)).is_err());
+        assert_eq!(storage.len(), 2);
+
+        let mut storage = quota_storage(2, EvictionPolicy::Lru);
+        storage.try_put([1; 32], entry(10)).unwrap();
+        storage.try_put([2; 32], entry(20)).unwrap();
+        storage.get(&[1; 32]);
+        storage.try_put([3; 32], entry(30)).unwrap();
+        assert!(storage.get(&[1; 32]).is_some());
+        assert!(storage.get(&[2; 32]).is_none());
+
+        let mut storage = quota_storage(2, EvictionPolicy::EarliestExpiry);
+        storage.try_put([1; 32], entry(20)).unwrap();
+        storage.try_put([2; 32], entry(10)).unwrap();
+        storage.try_put([3; 32], entry(30)).unwrap();
+        assert!(storage.get(&[1; 32]).is_some());
+        assert!(storage.get(&[2; 32]).is_none());
+        assert_eq!(storage.stats_arc.lock().unwrap().evicted, 1);
+    }
+
+    #[test]
+    fn test_eviction_order_follows_updates() {
+        let mut storage = quota_storage(2, EvictionPolicy::EarliestExpiry);
+        storage.try_put([1; 32], entry(10)).unwrap();
+        storage.try_put([2; 32], entry(20)).unwrap();
+        storage.try_put([1; 32], entry(30)).unwrap();
+        storage.try_put([3; 32], entry(40)).unwrap();
+        assert!(storage.get(&[1; 32]).is_some());
+        assert!(storage.get(&[2; 32]).is_none());
+
+        storage.remove(&[1; 32]);
+        storage.try_put([4; 32], entry(50)).unwrap();
+        storage.try_put([5; 32], entry(60)).unwrap();
+        assert!(storage.get(&[3; 32]).is_none());
+        assert_eq!(storage.eviction_order.borrow().len(), storage.len());
+    }
+
+    #[test]
+    fn test_oversized_entry_is_rejected_without_eviction() {
+        let quota = StorageQuota { max_bytes: Some(100), max_entries: None, eviction_policy: EvictionPolicy::Lru };
+        let mut storage = QuotaStorage::new(Box::new(MemoryStorage::new()), quota, Arc::new(Mutex::new(QuotaStats::default())));
+        storage.try_put([1; 32], entry(10)).unwrap();
+        storage.try_put([2; 32], entry(20)).unwrap();
+
+        let oversized = KvEntry { value: vec![0; 100], ..entry(30) };
+        assert!(storage.try_put([3; 32], oversized).is_err());
+        assert!(storage.get(&[1; 32]).is_some());
+        assert!(storage.get(&[2; 32]).is_some());
+        assert_eq!(storage.stats_arc.lock().unwrap().evicted, 0);
+
+        let mut storage = quota_storage(0, EvictionPolicy::Lru);
+        assert!(storage.try_put([1; 32], entry(10)).is_err());
+    }
+
+    #[test]
+    fn test_tombstones_are_not_evicted() {
+        let mut storage = quota_storage(2, EvictionPolicy::EarliestExpiry);
+        storage.try_put([1; 32], KvEntry { tombstone: true, ..entry(10) }).unwrap();
+        storage.try_put([2; 32], entry(20)).unwrap();
+        storage.try_put([3; 32], entry(30)).unwrap();
+        assert!(storage.get(&[1; 32]).is_some_and(|entry| entry.tombstone));
+        assert!(storage.get(&[2; 32]).is_none());
+        assert!(storage.get(&[3; 32]).is_some());
+    }
+
+    #[test]
+    fn test_quota_is_shared_between_virtual_nodes() {
+        let quota = StorageQuota { max_bytes: None, max_entries: Some(2), eviction_policy: EvictionPolicy::Reject };
+        let stats_arc = Arc::new(Mutex::new(QuotaStats::default()));
+        let mut first_storage = QuotaStorage::new(Box::new(MemoryStorage::new()), quota.clone(), stats_arc.clone());
+        let mut second_storage = QuotaStorage::new(Box::new(MemoryStorage::new()), quota, stats_arc.clone());
+
+        first_storage.try_put([1; 32], entry(10)).unwrap();
+        second_storage.try_put([2; 32], entry(10)).unwrap();
+        assert!(first_storage.try_put([3; 32], entry(10)).is_err());
+
+        second_storage.remove(&[2; 32]);
+        assert!(first_storage.try_put([3; 32], entry(10)).is_ok());
+        assert_eq!(stats_arc.lock().unwrap().entries, 2);
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::io;
+
+use crate::utils::time::has_expired;
+use crate::utils::types::{HashPos, Key, KvEntry};
+
+/// Error returned if an entry could not be stored
+#[derive(Debug)]
+pub enum PutError {
+    /// the entry does not fit into the storage quota
+    QuotaExceeded,
+    /// the entry could not be written to durable storage
+    Io(io::Error),
+}
+
+impl Display for PutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PutError::QuotaExceeded => write!(f, "storage quota exceeded"),
+            PutError::Io(err) => write!(f, "failed to write to durable storage: {}", err),
+        }
+    }
+}
+
+/// Interface of a node's key value storage. The RPC handlers and maintenance threads only use
+/// this trait, so storage engines can be exchanged without touching them.
+pub trait Storage: Send + Debug {
+    fn get(&self, key: &Key) -> Option<KvEntry>;
+
+    /// stores the entry and returns the previously stored one, if any
+    fn put(&mut self, key: Key, entry: KvEntry) -> Option<KvEntry>;
+
+    /// like put, but fails instead of storing the entry if it exceeds the storage quota or can
+    /// not be persisted. Engines without a quota or persistence always store the entry.
+    fn try_put(&mut self, key: Key, entry: KvEntry) -> Result<Option<KvEntry>, PutError> {
+        Ok(self.put(key, entry))
+    }
+
+    fn remove(&mut self, key: &Key) -> Option<KvEntry>;
+
+    /// returns all pairs whose key hashes into the ring interval [lower, upper]
+    fn range(&self, lower: HashPos, upper: HashPos) -> Vec<(Key, KvEntry)>;
+
+    fn iter(&self) -> Box<dyn Iterator<Item=(&Key, &KvEntry)> + '_>;
+
+    fn len(&self) -> usize;
+
+    /// removes all expired pairs and returns how many were removed
+    fn remove_expired(&mut self) -> usize {
+        let expired_keys: Vec<Key> = self.iter()
+            .filter(|(_, entry)| has_expired(&entry.expiration_date))
+            .map(|(key, _)| *key)
+            .collect();
+        for key in expired_keys.iter() {
+            self.remove(key);
+        }
+        expired_keys.len()
+    }
+
+    /// whether the data survives a restart of the node
+    fn is_persistent(&self) -> bool {
+        false
+    }
+
+    /// writes the current data to durable storage, engines without persistence do nothing
+    fn snapshot(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The storage type shared between the gRPC service and the threads
+pub type KvStore = dyn Storage;
@@ -1,10 +1,9 @@
 use crate::utils::types::Address;
 
-pub const SUCCESSOR_LIST_SIZE: usize = 3;
-
-/// Instead of only the direct successor, each node stores the urls of its 3 next successors.
-/// This makes the cluster more robust and allows it to continue running even if 2 direct neighbor 
-/// fail at the same time.
+/// Instead of only the direct successor, each node stores the urls of its next successors, the
+/// number is configured with `successor_list_size`. This makes the cluster more robust and allows
+/// it to continue running even if all but one of these successors fail at the same time.
+/// On rings with fewer nodes than the list size, the list contains every other node exactly once.
 
 #[derive(Default, Debug, Clone)]
 pub struct SuccessorList {
@@ -20,11 +19,54 @@ impl SuccessorList {
         }
     }
 
-    pub fn update_with_other_succ_list(&mut self, other_list: SuccessorList) -> () {
-        self.successors = other_list.successors.clone();
-        self.successors.insert(0, other_list.own_address.clone());
-        if self.successors.len() > SUCCESSOR_LIST_SIZE {
-            self.successors.pop();
+    /// replaces the successors with the direct successor followed by its successors. Where the
+    /// list wraps around the ring, this node and duplicates are skipped. A node that is alone in
+    /// the ring is its own successor.
+    pub fn update_with_other_succ_list(&mut self, other_list: SuccessorList, size: usize) {
+        let mut successors: Vec<Address> = Vec::new();
+        for successor in std::iter::once(other_list.own_address).chain(other_list.successors) {
+            if successors.len() == size {
+                break;
+            }
+            if successor.ne(&self.own_address) && !successor.is_empty() && !successors.contains(&successor) {
+                successors.push(successor);
+            }
+        }
+        if successors.is_empty() {
+            successors.push(self.own_address.clone());
+        }
+        self.successors = successors;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn successor_list(own_address: &str, successors: &[&str]) -> SuccessorList {
+        SuccessorList {
+            own_address: own_address.to_string(),
+            successors: successors.iter().map(|successor| successor.to_string()).collect(),
         }
     }
+
+    #[test]
+    fn test_update_skips_own_address_and_duplicates() {
+        let mut list = successor_list("a", &["b"]);
+        list.update_with_other_succ_list(successor_list("b", &["c", "d", "e"]), 3);
+        assert_eq!(list.successors, vec!["b", "c", "d"]);
+
+        // ring of three nodes, the successor's list wraps around to this node
+        list.update_with_other_succ_list(successor_list("b", &["c", "a", "b"]), 3);
+        assert_eq!(list.successors, vec!["b", "c"]);
+
+        // ring of two nodes
+        list.update_with_other_succ_list(successor_list("b", &["a", "b", "a"]), 3);
+        assert_eq!(list.successors, vec!["b"]);
+
+        // node is alone in the ring
+        list.update_with_other_succ_list(successor_list("a", &["a"]), 3);
+        assert_eq!(list.successors, vec!["a"]);
+    }
 }
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use blake3::Hasher;
+
+use crate::utils::types::{Address, HashPos, Key, KvEntry, Version};
+
+/// Range handed to a new predecessor in notify. The pairs stay in the local store until the
+/// receiver acknowledges the transfer with the same count and checksum, so a transfer that is
+/// interrupted loses no data and can be repeated.
+#[derive(Debug, Clone)]
+pub struct PendingTransfer {
+    /// the node that receives the range
+    pub address: Address,
+    pub lower: HashPos,
+    pub upper: HashPos,
+    /// versions of the transferred pairs, a pair that changed in the meantime is kept
+    pub versions: Vec<(Key, Version)>,
+    pub checksum: u64,
+    pub started: Instant,
+}
+
+impl PendingTransfer {
+    pub fn new(address: &Address, lower: HashPos, upper: HashPos, pairs: &[(Key, KvEntry)]) -> Self {
+        PendingTransfer {
+            address: address.clone(),
+            lower,
+            upper,
+            versions: pairs.iter().map(|(key, entry)| (*key, entry.version)).collect(),
+            checksum: transfer_checksum(pairs),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn is_acknowledged_by(&self, count: u64, checksum: u64) -> bool {
+        self.versions.len() as u64 == count && self.checksum == checksum
+    }
+}
+
+/// Unacknowledged transfers by receiver and range. Several nodes may join between this node and
+/// its predecessor at the same time, each of them is handed its own range.
+#[derive(Debug, Default)]
+pub struct PendingTransfers {
+    transfers: HashMap<(Address, HashPos, HashPos), PendingTransfer>,
+}
+
+impl PendingTransfers {
+    /// replaces an earlier transfer of the same range to the same node
+    pub fn insert(&mut self, transfer: PendingTransfer) {
+        self.transfers.insert((transfer.address.clone(), transfer.lower, transfer.upper), transfer);
+    }
+
+    /// forgets an earlier transfer of the range to the node, e.g. because the range is empty now
+    pub fn remove(&mut self, address: &Address, lower: HashPos, upper: HashPos) {
+        self.transfers.remove(&(address.clone(), lower, upper));
+    }
+
+    /// returns the range of the oldest transfer to the node that has not been acknowledged
+    /// within the timeout
+    pub fn next_retry(&self, address: &Address, timeout: Duration) -> Option<(HashPos, HashPos)> {
+        self.transfers.values()
+            .filter(|transfer| transfer.address.eq(address) && transfer.started.elapsed() > timeout)
+            .min_by_key(|transfer| transfer.started)
+            .map(|transfer| (transfer.lower, transfer.upper))
+    }
+
+    /// returns the transfer to the node that the count and checksum belong to
+    pub fn get(&self, address: &Address, count: u64, checksum: u64) -> Option<&PendingTransfer> {
+        self.transfers.values()
+            .find(|transfer| transfer.address.eq(address) && transfer.is_acknowledged_by(count, checksum))
+    }
+
+    /// removes and returns the transfer to the node that the count and checksum belong to
+    pub fn take(&mut self, address: &Address, count: u64, checksum: u64) -> Option<PendingTransfer> {
+        let transfer = self.get(address, count, checksum)?;
+        let id = (transfer.address.clone(), transfer.lower, transfer.upper);
+        self.transfers.remove(&id)
+    }
+}
+
+/// checksum over the keys, versions and values of the pairs in the order they are streamed
+pub fn transfer_checksum(pairs: &[(Key, KvEntry)]) -> u64 {
+    let mut hasher = Hasher::new();
+    for (key, entry) in pairs {
+        hasher.update(key);
+        hasher.update(&entry.version.to_be_bytes());
+        hasher.update(&entry.value);
+    }
+    let bytes = *hasher.finalize().as_bytes();
+    u64::from_be_bytes(bytes[0..8].try_into().unwrap())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(key_byte: u8, version: Version) -> (Key, KvEntry) {
+        ([key_byte; 32], KvEntry { value: b"value".to_vec(), version, ..KvEntry::default() })
+    }
+
+    #[test]
+    fn test_transfer_is_acknowledged_by_matching_count_and_checksum() {
+        let pairs = vec![pair(1, 1), pair(2, 3)];
+        let transfer = PendingTransfer::new(&"127.0.0.1:5601".to_string(), 0, 10, &pairs);
+        assert!(transfer.is_acknowledged_by(2, transfer_checksum(&pairs)));
+        assert!(!transfer.is_acknowledged_by(1, transfer_checksum(&pairs[..1])));
+
+        // a pair received in another version does not match
+        let changed_pairs = vec![pair(1, 1), pair(2, 4)];
+        assert!(!transfer.is_acknowledged_by(2, transfer_checksum(&changed_pairs)));
+    }
+
+    #[test]
+    fn test_pending_transfers_are_kept_per_receiver_and_range() {
+        let first_address = "127.0.0.1:5601".to_string();
+        let second_address = "127.0.0.1:5602".to_string();
+        let first_pairs = vec![pair(1, 1)];
+        let second_pairs = vec![pair(2, 1)];
+        let mut pending_transfers = PendingTransfers::default();
+        pending_transfers.insert(PendingTransfer::new(&first_address, 0, 10, &first_pairs));
+        pending_transfers.insert(PendingTransfer::new(&second_address, 10, 20, &second_pairs));
+
+        // an ack only matches the transfer to its sender
+        assert!(pending_transfers.take(&second_address, 1, transfer_checksum(&first_pairs)).is_none());
+        let first_transfer = pending_transfers.take(&first_address, 1, transfer_checksum(&first_pairs)).unwrap();
+        assert_eq!((first_transfer.lower, first_transfer.upper), (0, 10));
+        assert!(pending_transfers.get(&second_address, 1, transfer_checksum(&second_pairs)).is_some());
+
+        assert_eq!(pending_transfers.next_retry(&second_address, Duration::ZERO), Some((10, 20)));
+        assert_eq!(pending_transfers.next_retry(&first_address, Duration::ZERO), None);
+    }
+}
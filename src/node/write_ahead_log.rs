@@ -0,0 +1,150 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use log::warn;
+
+use crate::utils::types::{Key, KvEntry};
+
+const WAL_FILE_NAME: &str = "wal.log";
+const SNAPSHOT_FILE_NAME: &str = "snapshot.bin";
+const SNAPSHOT_TMP_FILE_NAME: &str = "snapshot.bin.tmp";
+
+const OP_PUT: u8 = 1;
+const OP_REMOVE: u8 = 2;
+
+/// A single mutation of the storage as it is written to disk
+pub enum LogRecord {
+    Put(Key, KvEntry),
+    Remove(Key),
+}
+
+/// On-disk representation of a DiskStorage, consisting of a snapshot file and a write-ahead log.
+/// Every mutation is appended to the log before it is applied in memory. Taking a snapshot writes
+/// the full store to a new snapshot file and truncates the log afterwards.
+///
+/// Both files are a sequence of records of the following format (all integers big endian):
+/// op (u8) | key (32 bytes) | expiration_date (u64) | replication (u32) | timestamp (u64) | tombstone (u8) | version (u64) | value length (u32) | value
+/// where remove records only consist of op and key.
+#[derive(Debug)]
+pub struct WriteAheadLog {
+    data_dir: PathBuf,
+    wal_file: File,
+}
+
+impl WriteAheadLog {
+    /// opens the log in the given directory, creating the directory if necessary, and returns all
+    /// records of the snapshot followed by all records of the log
+    pub fn open(data_dir: &Path) -> io::Result<(WriteAheadLog, Vec<LogRecord>)> {
+        std::fs::create_dir_all(data_dir)?;
+
+        let mut records = read_records(&data_dir.join(SNAPSHOT_FILE_NAME))?;
+        records.extend(read_records(&data_dir.join(WAL_FILE_NAME))?);
+
+        let wal_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(data_dir.join(WAL_FILE_NAME))?;
+
+        Ok((WriteAheadLog { data_dir: data_dir.to_path_buf(), wal_file }, records))
+    }
+
+    pub fn append(&mut self, record: &LogRecord) -> io::Result<()> {
+        self.wal_file.write_all(&encode(record))
+    }
+
+    /// atomically replaces the snapshot with the given entries and truncates the log
+    pub fn write_snapshot<'a>(&mut self, entries: impl Iterator<Item=(&'a Key, &'a KvEntry)>) -> io::Result<()> {
+        let tmp_path = self.data_dir.join(SNAPSHOT_TMP_FILE_NAME);
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            for (key, entry) in entries {
+                writer.write_all(&encode(&LogRecord::Put(*key, entry.clone())))?;
+            }
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+        }
+        std::fs::rename(&tmp_path, self.data_dir.join(SNAPSHOT_FILE_NAME))?;
+
+        self.wal_file.set_len(0)?;
+        self.wal_file.sync_all()
+    }
+}
+
+fn encode(record: &LogRecord) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    match record {
+        LogRecord::Put(key, entry) => {
+            bytes.push(OP_PUT);
+            bytes.extend_from_slice(key);
+            bytes.extend_from_slice(&entry.expiration_date.to_be_bytes());
+            bytes.extend_from_slice(&entry.replication.to_be_bytes());
+            bytes.extend_from_slice(&entry.timestamp.to_be_bytes());
+            bytes.push(entry.tombstone as u8);
+            bytes.extend_from_slice(&entry.version.to_be_bytes());
+            bytes.extend_from_slice(&(entry.value.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&entry.value);
+        }
+        LogRecord::Remove(key) => {
+            bytes.push(OP_REMOVE);
+            bytes.extend_from_slice(key);
+        }
+    }
+    bytes
+}
+
+/// reads all complete records of a file. A truncated record at the end of the file, which is left
+/// behind if the process died while appending, is skipped.
+fn read_records(path: &Path) -> io::Result<Vec<LogRecord>> {
+    let mut records = Vec::new();
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(records),
+        Err(err) => return Err(err),
+    };
+    let mut reader = BufReader::new(file);
+    loop {
+        match read_record(&mut reader) {
+            Ok(Some(record)) => records.push(record),
+            Ok(None) => break,
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                warn!("Skipping truncated record at the end of {:?}", path);
+                break;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(records)
+}
+
+fn read_record(reader: &mut impl Read) -> io::Result<Option<LogRecord>> {
+    let mut op = [0u8; 1];
+    if reader.read(&mut op)? == 0 {
+        return Ok(None);
+    }
+    let mut key: Key = [0; 32];
+    reader.read_exact(&mut key)?;
+    match op[0] {
+        OP_PUT => {
+            let mut u64_buf = [0u8; 8];
+            let mut u32_buf = [0u8; 4];
+            reader.read_exact(&mut u64_buf)?;
+            let expiration_date = u64::from_be_bytes(u64_buf);
+            reader.read_exact(&mut u32_buf)?;
+            let replication = u32::from_be_bytes(u32_buf);
+            reader.read_exact(&mut u64_buf)?;
+            let timestamp = u64::from_be_bytes(u64_buf);
+            let mut tombstone = [0u8; 1];
+            reader.read_exact(&mut tombstone)?;
+            reader.read_exact(&mut u64_buf)?;
+            let version = u64::from_be_bytes(u64_buf);
+            reader.read_exact(&mut u32_buf)?;
+            let mut value = vec![0u8; u32::from_be_bytes(u32_buf) as usize];
+            reader.read_exact(&mut value)?;
+            Ok(Some(LogRecord::Put(key, KvEntry { value, expiration_date, replication, timestamp, tombstone: tombstone[0] != 0, version })))
+        }
+        OP_REMOVE => Ok(Some(LogRecord::Remove(key))),
+        op => Err(io::Error::new(ErrorKind::InvalidData, format!("Invalid log record type {}", op))),
+    }
+}
@@ -1,6 +1,7 @@
+use std::mem;
 use std::pin::Pin;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use log::{debug, error, info, warn};
 use tokio::sync::mpsc;
@@ -8,21 +9,28 @@ use tokio::sync::oneshot::Receiver;
 use tokio::time::sleep;
 use tokio_stream::Stream;
 use tokio_stream::wrappers::UnboundedReceiverStream;
-use tonic::{Request, Response, Status, Streaming};
-use tonic::transport::Channel;
-
-use chord::utils::types::{Address, HashPos, Key, KvStore};
+use tonic::{Code, Request, Response, Status, Streaming};
+use tonic::transport::{Channel, Endpoint};
 
+use crate::node::channel_pool::channel_pool;
+use crate::node::failure_detector::failure_detector;
 use crate::node::finger_entry::FingerEntry;
 use crate::node::finger_table::FingerTable;
+use crate::node::lookup_cache::lookup_cache;
+use crate::node::metrics::{LookupStats, RttTable};
+use crate::node::storage::{KvStore, PutError};
+use crate::node::predecessor_list::PredecessorList;
 use crate::node::successor_list::SuccessorList;
-use crate::threads::chord::chord_proto::{AddressMsg, Empty, FingerEntryMsg, GetKvStoreDataResponse, GetKvStoreSizeResponse, GetPredecessorResponse, GetRequest, GetResponse, GetStatus, HashPosMsg, KvPairDebugMsg, KvPairMsg, NodeSummaryMsg, NotifyRequest, PowTokenMsg, PutRequest, SuccessorListMsg};
+use crate::node::transfer::{PendingTransfer, PendingTransfers, transfer_checksum};
+use crate::threads::chord::chord_proto::{Empty, FindSuccessorResponse, FindSuccessorsRequest, FindSuccessorsResponse, FingerEntryMsg, FingerTableMsg, GetKvStoreDataResponse, GetKvStoreSizeResponse, GetPredecessorResponse, GetReplicaResponse, GetRequest, GetResponse, GetStatus, HashPosMsg, KvPairDebugMsg, KvPairMsg, LeaveRequest, LookupHopMsg, LookupMode, LookupRequest, LookupResponse, NodeSummaryMsg, NotifyRequest, PowTokenMsg, PredecessorListMsg, PutRequest, PutResponse, PutStatus, RemoveRequest, RemoveResponse, RemoveStatus, ReplicaStatusMsg, SuccessorListMsg, TransferAckMsg};
 use crate::threads::chord::chord_proto::chord_client::ChordClient;
-use crate::utils::constants::DEBUG_RPCS_UNAVAILABLE_ERROR_MESSAGE;
+use crate::threads::setup::GrpcServiceState;
+use crate::utils::constants::{DEBUG_RPCS_UNAVAILABLE_ERROR_MESSAGE, DRAINED_ERROR_MESSAGE, NOT_RESPONSIBLE_ERROR_MESSAGE, TOMBSTONE_LIVE_TIME, TRANSFER_ACK_TIMEOUT_MILLIS};
+use chord::utils::config::{Config, is_same_process};
 use crate::utils::crypto::{hash, HashRingKey, is_between};
 use crate::utils::proof_of_work::PowToken;
 use crate::utils::time::{has_expired, now};
-use crate::utils::types::ExpirationDate;
+use crate::utils::types::{Address, HashPos, Key, KvEntry, Replication, Version};
 
 pub mod chord_proto {
     tonic::include_proto!("chord");
@@ -45,10 +53,26 @@ pub struct ChordService {
     fix_finger_index: Arc<Mutex<usize>>,
     /// list of the next n successor
     successor_list: Arc<Mutex<SuccessorList>>,
+    /// list of the next n predecessors, maintained by the predecessor health check
+    predecessor_list: Arc<Mutex<PredecessorList>>,
     /// required number of trailing 0 bytes for a POW token to be valid
     pow_difficulty: usize,
     /// flag that enables debugging RPCs
-    dev_mode: bool
+    dev_mode: bool,
+    /// number of replicas (including this node) that are read on GET
+    read_quorum: u32,
+    /// lookup mode used if a lookup request does not choose one
+    lookup_mode: LookupMode,
+    /// configured number of successors kept in the successor list
+    successor_list_size: usize,
+    /// measured round trip times to other nodes, used to select fingers
+    rtt_table: Arc<Mutex<RttTable>>,
+    /// latency of the lookups handled by this node
+    lookup_stats: Arc<Mutex<LookupStats>>,
+    /// set while the node is in maintenance, a drained node owns no range and only forwards
+    drained: Arc<Mutex<bool>>,
+    /// ranges handed to new predecessors in notify that have not been acknowledged yet
+    pending_transfers: Arc<Mutex<PendingTransfers>>,
 }
 
 const MAX_RETRIES: u64 = 15;
@@ -56,12 +80,45 @@ const CONNECTION_RETRY_SLEEP: u64 = 100;
 
 /// connection helper functions
 
+/// returns a client on the pooled channel to the given address, a new channel is only opened if
+/// there is none or it has been idle for too long
 pub(crate) async fn connect(address: &Address) -> Result<ChordClient<Channel>, tonic::transport::Error> {
-    ChordClient::connect(format!("http://{}", address)).await
+    let channel_option = channel_pool().lock().unwrap().get(address);
+    let channel = match channel_option {
+        Some(channel) => channel,
+        None => {
+            let channel = Endpoint::from_shared(format!("http://{}", address))?.connect().await?;
+            channel_pool().lock().unwrap().insert(address, channel.clone());
+            channel
+        }
+    };
+    Ok(ChordClient::new(channel))
+}
+
+/// returns a client for the local gRPC service. The service only starts once the node has set up
+/// its data structures, which takes as long as joining the ring, so this waits until it is up.
+pub(crate) async fn connect_to_local_service(address: &Address) -> ChordClient<Channel> {
+    loop {
+        match connect(address).await {
+            Ok(client) => return client,
+            Err(_) => sleep(Duration::from_millis(CONNECTION_RETRY_SLEEP)).await
+        }
+    }
 }
 
-pub(crate) async fn connect_without_retry(address: &Address) -> ChordClient<Channel> {
-    connect(address).await.unwrap()
+/// whether the status indicates a broken connection. A connection that breaks during a call
+/// surfaces as an unknown status caused by a transport error.
+pub(crate) fn is_connection_error(status: &Status) -> bool {
+    status.code() == Code::Unavailable || std::error::Error::source(status)
+        .is_some_and(|source| source.is::<tonic::transport::Error>())
+}
+
+/// drops the pooled channel to the given address if the status indicates a broken connection,
+/// so that the next connect does not reuse it
+pub(crate) fn evict_if_unavailable(address: &Address, status: &Status) {
+    if is_connection_error(status) {
+        channel_pool().lock().unwrap().evict(address);
+    }
 }
 
 pub(crate) async fn connect_with_retry(address: &Address) -> Result<ChordClient<Channel>, Status> {
@@ -70,6 +127,9 @@ pub(crate) async fn connect_with_retry(address: &Address) -> Result<ChordClient<
         match connect(address).await {
             Ok(client) => return Ok(client),
             Err(e) => {
+                if !failure_detector().lock().unwrap().is_available(address) {
+                    return Err(Status::unavailable(format!("{} is suspected to have failed", address)));
+                }
                 retries += 1;
                 if retries > MAX_RETRIES {
                     // You can return an error or handle it differently
@@ -83,19 +143,131 @@ pub(crate) async fn connect_with_retry(address: &Address) -> Result<ChordClient<
     }
 }
 
-pub(crate) async fn connect_to_first_reachable_node(address_list: &Vec<Address>) -> Option<(ChordClient<Channel>, Address)> {
-    for address in address_list {
-        if let Ok(successor_client) = connect_with_retry(address).await {
-            return Some((successor_client, address.clone()));
+/// sends a health call to every suspected node among the given addresses and records the outcome
+/// in the failure detector, so that nodes which answer again are no longer suspected
+pub(crate) async fn probe_suspected(addresses: &[Address]) {
+    let suspected: Vec<Address> = addresses.iter()
+        .filter(|address| !failure_detector().lock().unwrap().is_available(address))
+        .cloned()
+        .collect();
+    for address in suspected {
+        let response_result = match connect(&address).await {
+            Ok(mut client) => client.health(Request::new(Empty {})).await,
+            Err(e) => Err(Status::unavailable(e.to_string()))
+        };
+        match response_result {
+            Ok(_) => failure_detector().lock().unwrap().heartbeat(&address),
+            Err(status) => {
+                failure_detector().lock().unwrap().missed_heartbeat(&address);
+                evict_if_unavailable(&address, &status);
+            }
+        }
+    }
+}
+
+/// sends the given key value pairs to the node with the given address, which stores them as replicas
+pub(crate) async fn send_replicas(address: &Address, pairs: Vec<KvPairMsg>) -> Result<(), Status> {
+    let mut client = connect_with_retry(address).await?;
+    client.replicate(Request::new(tokio_stream::iter(pairs))).await
+        .inspect_err(|status| evict_if_unavailable(address, status))?;
+    Ok(())
+}
+
+/// version a client observes for the stored entry, removed and expired keys have version 0
+fn visible_version(entry_option: &Option<KvEntry>) -> Version {
+    match entry_option {
+        Some(entry) if !entry.tombstone && !has_expired(&entry.expiration_date) => entry.version,
+        _ => 0
+    }
+}
+
+/// pooled channels may point to nodes that have left, so every node is probed with a health call.
+/// Nodes suspected by the failure detector are only tried after all others.
+pub(crate) async fn connect_to_first_reachable_node(address_list: &[Address]) -> Option<(ChordClient<Channel>, Address)> {
+    let (available, suspected): (Vec<&Address>, Vec<&Address>) = address_list.iter()
+        .partition(|address| failure_detector().lock().unwrap().is_available(address));
+    for address in available.into_iter().chain(suspected) {
+        if let Ok(mut successor_client) = connect_with_retry(address).await {
+            match successor_client.health(Request::new(Empty {})).await {
+                Ok(_) => {
+                    failure_detector().lock().unwrap().heartbeat(address);
+                    return Some((successor_client, address.clone()));
+                }
+                Err(status) => evict_if_unavailable(address, &status)
+            }
         }
     };
     None
 }
 
+/// hands all pairs of the store to the first reachable successor and then tells both neighbours
+/// that the node leaves the ring. The skipped addresses are never chosen to take over the range.
+/// Returns the address of the node that took over the range, None if no successor is reachable.
+pub(crate) async fn hand_off_and_leave(own_address: &Address, kv_store_arc: &Arc<Mutex<KvStore>>, successor_list: SuccessorList, predecessor_option: Option<Address>, skipped_addresses: &[Address]) -> Option<Address> {
+    let successors: Vec<Address> = successor_list.successors.into_iter()
+        .filter(|successor| successor.ne(own_address) && !skipped_addresses.contains(successor))
+        .collect();
+    let (mut successor_client, successor_address) = connect_to_first_reachable_node(&successors).await?;
+    info!("Selected successor for handoff");
+
+    let one = HashPos::one();
+    let pairs: Vec<(Key, KvEntry)> = kv_store_arc.lock().unwrap()
+        .range(one + 1, one)
+        .into_iter()
+        .filter(|(_, entry)| !has_expired(&entry.expiration_date))
+        .collect();
+    let pair_msgs: Vec<KvPairMsg> = pairs.iter().map(|(key, entry)| (key, entry).into()).collect();
+    match successor_client.handoff(Request::new(tokio_stream::iter(pair_msgs))).await {
+        Ok(_) => {
+            // stale copies would otherwise be handed off again on a later leave, or served again
+            // after an undrain. Pairs written in the meantime are kept.
+            let mut kv_store = kv_store_arc.lock().unwrap();
+            for (key, entry) in pairs.iter() {
+                if kv_store.get(key).is_some_and(|stored_entry| stored_entry.version == entry.version) {
+                    kv_store.remove(key);
+                }
+            }
+            info!("Transfered {} key-value-pairs to {}", pairs.len(), successor_address)
+        }
+        Err(e) => warn!("Failed to transfer key-value-pairs to {}: {}", successor_address, e)
+    }
+
+    // the predecessor's new successor list starts with the node that received the data
+    let remaining_successor_list = SuccessorList {
+        own_address: own_address.clone(),
+        successors: successors.into_iter()
+            .skip_while(|successor| successor.ne(&successor_address))
+            .collect(),
+    };
+    let leave_request = LeaveRequest {
+        address: Some(own_address.clone().into()),
+        predecessor: predecessor_option.clone().map(|address| address.into()),
+        successor_list: Some(remaining_successor_list.into()),
+    };
+
+    let mut neighbours = vec![successor_address.clone()];
+    if let Some(predecessor_address) = predecessor_option {
+        if predecessor_address.ne(&successor_address) && predecessor_address.ne(own_address) && !skipped_addresses.contains(&predecessor_address) {
+            neighbours.push(predecessor_address);
+        }
+    }
+    for neighbour in neighbours {
+        let result = match connect_with_retry(&neighbour).await {
+            Ok(mut client) => client.leave(Request::new(leave_request.clone())).await.map(|_| ()),
+            Err(status) => Err(status)
+        };
+        match result {
+            Ok(()) => info!("{} acknowledged that {} leaves the ring", neighbour, own_address),
+            Err(e) => warn!("{} did not acknowledge that {} leaves the ring: {}", neighbour, own_address, e)
+        }
+    }
+    Some(successor_address)
+}
+
 
 impl ChordService {
-    pub async fn new(rx: Receiver<(Arc<Mutex<FingerTable>>, Arc<Mutex<Option<FingerEntry>>>, Arc<Mutex<KvStore>>, Arc<Mutex<SuccessorList>>)>, url: &String, pow_difficulty: usize, dev_mode: bool) -> ChordService {
-        let (finger_table_arc, predecessor_option_arc, kv_store_arc, successor_list_arc) = rx.await.unwrap();
+    pub async fn new(rx: Receiver<GrpcServiceState>, url: &String, config: &Config, lookup_stats_arc: Arc<Mutex<LookupStats>>) -> ChordService {
+        let (finger_table_arc, predecessor_option_arc, kv_store_arc, successor_list_arc, predecessor_list_arc) = rx.await.unwrap();
         ChordService {
             address: url.clone(),
             pos: hash(&url.as_bytes()),
@@ -104,9 +276,69 @@ impl ChordService {
             kv_store: kv_store_arc,
             fix_finger_index: Arc::new(Mutex::new(0)),
             successor_list: successor_list_arc,
-            pow_difficulty,
-            dev_mode
+            predecessor_list: predecessor_list_arc,
+            pow_difficulty: config.pow_difficulty,
+            dev_mode: config.dev_mode,
+            read_quorum: config.read_quorum,
+            lookup_mode: config.lookup_mode.into(),
+            successor_list_size: config.successor_list_size,
+            rtt_table: Arc::new(Mutex::new(RttTable::default())),
+            lookup_stats: lookup_stats_arc,
+            drained: Arc::new(Mutex::new(false)),
+            pending_transfers: Arc::new(Mutex::new(PendingTransfers::default())),
+        }
+    }
+
+    fn is_drained(&self) -> bool {
+        *self.drained.lock().unwrap()
+    }
+
+    /// measures the round trip time of a health call to the given node and records it in the rtt
+    /// table, returns None if the node is unreachable. The call doubles as heartbeat for fingers,
+    /// which are not probed by the maintenance threads.
+    pub async fn measure_rtt(&self, address: &Address) -> Option<Duration> {
+        let start = Instant::now();
+        let response_result = match connect(address).await {
+            Ok(mut client) => client.health(Request::new(Empty {})).await,
+            Err(e) => Err(Status::unavailable(e.to_string()))
+        };
+        let sample = start.elapsed();
+        if let Err(status) = response_result {
+            failure_detector().lock().unwrap().missed_heartbeat(address);
+            evict_if_unavailable(address, &status);
+            return None;
+        }
+        failure_detector().lock().unwrap().heartbeat(address);
+        let mut rtt_table = self.rtt_table.lock().unwrap();
+        rtt_table.record(address, sample);
+        rtt_table.get(address)
+    }
+
+    /// proximity neighbor selection: any node within the finger interval [start, end) is a valid
+    /// finger, so among the node responsible for start and its successors within the interval the
+    /// one with the lowest round trip time is chosen. Suspected candidates are probed as well, so
+    /// that the failure detector clears them once they answer again.
+    pub async fn select_closest_finger(&self, responsible_node_address: Address, start: HashPos, end: HashPos) -> Address {
+        let mut candidates = vec![responsible_node_address.clone()];
+        if let Ok(mut responsible_node_client) = connect(&responsible_node_address).await {
+            if let Ok(response) = responsible_node_client.get_successor_list(Request::new(Empty {})).await {
+                let successor_list: SuccessorList = response.into_inner().into();
+                candidates.extend(successor_list.successors.into_iter()
+                    .filter(|successor| is_between(hash(successor.as_bytes()), start, end, false, true))
+                    .filter(|successor| successor.ne(&self.address)));
+            }
+        }
+        candidates.dedup();
+
+        let mut closest_option: Option<(Address, Duration)> = None;
+        for candidate in candidates {
+            if let Some(rtt) = self.measure_rtt(&candidate).await {
+                if closest_option.as_ref().is_none_or(|(_, closest_rtt)| rtt < *closest_rtt) {
+                    closest_option = Some((candidate, rtt));
+                }
+            }
         }
+        closest_option.map(|(address, _)| address).unwrap_or(responsible_node_address)
     }
 
     pub async fn get_successor_address(&self) -> Address {
@@ -114,11 +346,197 @@ impl ChordService {
     }
 
     pub async fn set_successor(&self, new_successor_address: &Address) -> () {
-        self.successor_list.lock().unwrap().successors[0] = new_successor_address.clone();
+        let previous_successor_address = mem::replace(&mut self.successor_list.lock().unwrap().successors[0], new_successor_address.clone());
+        if previous_successor_address.ne(new_successor_address) {
+            lookup_cache().lock().unwrap().clear();
+        }
         self.finger_table.lock().unwrap().fingers[0].address = new_successor_address.clone();
     }
 
 
+    /// resolves the responsible node for a position by querying every node on the path from here,
+    /// instead of forwarding the lookup. Returns the responsible node and the visited hops.
+    pub async fn iterative_lookup(&self, key: &HashPos) -> Result<(Address, Vec<LookupHopMsg>), Status> {
+        let mut hops: Vec<LookupHopMsg> = Vec::new();
+        let mut current_address = self.address.clone();
+        for _ in 0..HashPos::finger_count() {
+            let start = Instant::now();
+            let mut client = connect_with_retry(&current_address).await?;
+            let successor_address = client.get_successor_list(Request::new(Empty {}))
+                .await
+                .inspect_err(|status| evict_if_unavailable(&current_address, status))?
+                .into_inner()
+                .successors.first()
+                .map(|successor| successor.address.clone())
+                .ok_or_else(|| Status::internal(format!("Node {} has an empty successor list", current_address)))?;
+
+            let next_address = if is_between(*key, hash(current_address.as_bytes()) + 1, hash(successor_address.as_bytes()), false, false) {
+                None
+            } else {
+                let closest_preceding_finger = client.find_closest_preceding_finger(Request::new(key.into()))
+                    .await?
+                    .into_inner();
+                Some(closest_preceding_finger.address).filter(|address| address.ne(&current_address))
+            };
+            hops.push(LookupHopMsg {
+                address: current_address.clone(),
+                latency_micros: start.elapsed().as_micros() as u64,
+            });
+
+            match next_address {
+                Some(address) => current_address = address,
+                // the node's own position belongs to the node itself
+                None if *key == hash(current_address.as_bytes()) => return Ok((current_address, hops)),
+                // either the key lies between the node and its successor, or the node knows no
+                // closer node, in both cases its successor is responsible
+                None => return Ok((successor_address, hops))
+            }
+        }
+        Err(Status::internal(format!("Lookup of {} did not finish within {} hops", key, hops.len())))
+    }
+
+    /// returns the closest successor from the successor list that precedes the key and is not
+    /// suspected. Used if the finger table has no live finger preceding the key left.
+    fn find_closest_preceding_successor(&self, key: HashPos) -> Option<Address> {
+        let successors = {
+            self.successor_list.lock().unwrap().successors.clone()
+        };
+        let failure_detector = failure_detector().lock().unwrap();
+        successors.into_iter()
+            .filter(|successor| successor.ne(&self.address))
+            .filter(|successor| is_between(hash(successor.as_bytes()), self.pos, key, true, true))
+            .rfind(|successor| failure_detector.is_available(successor))
+    }
+
+    /// returns the addresses of the next `replication` successors that run in distinct processes,
+    /// skipping this node and its sibling virtual nodes. Otherwise a single crashed process could
+    /// take all copies of a pair with it.
+    pub async fn get_replica_addresses(&self, replication: Replication) -> Vec<Address> {
+        let successors = {
+            self.successor_list.lock().unwrap().successors.clone()
+        };
+        let mut replica_addresses: Vec<Address> = Vec::new();
+        for successor in successors {
+            if !is_same_process(&successor, &self.address) && !replica_addresses.iter().any(|replica_address| is_same_process(replica_address, &successor)) {
+                replica_addresses.push(successor);
+            }
+        }
+        replica_addresses.truncate(replication as usize);
+        replica_addresses
+    }
+
+    /// Reads the key from this node and from the next read_quorum - 1 replicas and returns the
+    /// freshest entry. Replicas within the key's replication factor that answered with a stale or
+    /// missing entry are repaired by writing the freshest entry back to them.
+    pub async fn quorum_read(&self, key: &Key) -> (Option<KvEntry>, ReplicaStatusMsg) {
+        let local_entry_option = {
+            self.kv_store.lock().unwrap().get(key)
+        };
+
+        let mut replica_entries: Vec<(Address, Option<KvEntry>)> = Vec::new();
+        for replica_address in self.get_replica_addresses(self.read_quorum.saturating_sub(1)).await {
+            let response_result = match connect_with_retry(&replica_address).await {
+                Ok(mut replica_client) => replica_client.get_replica(Request::new(GetRequest {
+                    key: key.to_vec(),
+                })).await,
+                Err(status) => Err(status)
+            };
+            match response_result {
+                Ok(response) => {
+                    let entry_option = response.into_inner().pair
+                        .map(|pair| -> (Key, KvEntry) { pair.into() })
+                        .map(|(_, entry)| entry);
+                    replica_entries.push((replica_address, entry_option));
+                }
+                Err(e) => {
+                    evict_if_unavailable(&replica_address, &e);
+                    warn!("Replica {} did not respond to read: {}", replica_address, e)
+                }
+            }
+        }
+
+        let freshest_entry_option = replica_entries.iter()
+            .filter_map(|(_, entry_option)| entry_option.clone())
+            .chain(local_entry_option.clone())
+            .max_by_key(|entry| entry.timestamp);
+
+        let mut replica_status = ReplicaStatusMsg {
+            read_quorum: self.read_quorum,
+            responded: replica_entries.len() as u32 + 1,
+            repaired: 0,
+        };
+
+        if let Some(ref freshest_entry) = freshest_entry_option {
+            let is_stale = |entry_option: &Option<KvEntry>| match entry_option {
+                Some(entry) => entry.timestamp < freshest_entry.timestamp,
+                None => true
+            };
+            if is_stale(&local_entry_option) {
+                self.kv_store.lock().unwrap().put(*key, freshest_entry.clone());
+                replica_status.repaired += 1;
+            }
+            for (i, (replica_address, entry_option)) in replica_entries.iter().enumerate() {
+                if i >= freshest_entry.replication as usize || !is_stale(entry_option) {
+                    continue;
+                }
+                match send_replicas(replica_address, vec![(*key, freshest_entry.clone()).into()]).await {
+                    Ok(_) => {
+                        debug!("Repaired replica of key {:?} on {}", hash(key), replica_address);
+                        replica_status.repaired += 1;
+                    }
+                    Err(e) => warn!("Failed to repair replica on {}: {}", replica_address, e)
+                }
+            }
+        }
+        (freshest_entry_option, replica_status)
+    }
+
+    /// writes the entry to the next `replication` nodes of the successor list
+    pub async fn replicate_entry(&self, key: &Key, entry: &KvEntry) -> () {
+        if entry.replication == 0 {
+            return;
+        }
+        let pair: KvPairMsg = (key, entry).into();
+        for replica_address in self.get_replica_addresses(entry.replication).await {
+            match send_replicas(&replica_address, vec![pair.clone()]).await {
+                Ok(_) => debug!("Replicated key {:?} to {}", hash(key), replica_address),
+                Err(e) => warn!("Failed to replicate key {:?} to {}: {}", hash(key), replica_address, e)
+            }
+        }
+    }
+
+    /// returns an error if the key does not belong to the range this node is responsible for
+    pub async fn check_responsibility(&self, key: &Key) -> Result<(), Status> {
+        if self.is_drained() {
+            return Err(Status::internal(format!("Node ({}, {}) {}", self.address, self.pos, DRAINED_ERROR_MESSAGE)));
+        }
+        let predecessor_pos = {
+            if let Some(finger_entry) = self.predecessor_option.lock().unwrap().clone() {
+                hash(finger_entry.address.as_bytes())
+            } else {
+                return Err(Status::internal("Predecessor not set"));
+            }
+        };
+        if is_between(hash(key), predecessor_pos + 1, self.pos, false, false) {
+            Ok(())
+        } else {
+            error!("This node is responsible for interval ({}, {}] !", predecessor_pos, self.pos);
+            let msg = format!("Node ({}, {}) {} ({}, {}]", self.address, self.pos, NOT_RESPONSIBLE_ERROR_MESSAGE, predecessor_pos, self.pos);
+            Err(Status::internal(msg))
+        }
+    }
+
+    /// a drained node owns no range, the successor that took over its range serves the client
+    /// requests that still reach this node
+    pub async fn get_client_for_forwarding(&self) -> Result<(ChordClient<Channel>, Address), Status> {
+        let successors: Vec<Address> = self.successor_list.lock().unwrap().successors.iter()
+            .filter(|successor| successor.ne(&&self.address))
+            .cloned()
+            .collect();
+        connect_to_first_reachable_node(&successors).await
+            .ok_or_else(|| Status::unavailable(format!("Node {} {} and no successor is reachable", self.address, DRAINED_ERROR_MESSAGE)))
+    }
+
     pub async fn get_client_for_closest_successor(&self) -> (ChordClient<Channel>, Address) {
         let successors = {
             self.successor_list.lock().unwrap().successors.clone()
@@ -130,14 +548,15 @@ impl ChordService {
         }
     }
 
-    pub async fn get_predecessor_client(&self) -> Option<ChordClient<Channel>> {
+    /// returns a client for the predecessor, None if the predecessor is not set and an unavailable
+    /// status if it cannot be reached or is suspected to have failed
+    pub async fn get_predecessor_client(&self) -> Result<Option<ChordClient<Channel>>, Status> {
         let predecessor_option_clone = {
             self.predecessor_option.lock().unwrap().clone()
         };
-        if let Some(ref predecessor) = predecessor_option_clone {
-            Some(connect_with_retry(&predecessor.address).await.unwrap())
-        } else {
-            None
+        match predecessor_option_clone {
+            Some(ref predecessor) => connect_with_retry(&predecessor.address).await.map(Some),
+            None => Ok(None)
         }
     }
 }
@@ -146,11 +565,12 @@ impl ChordService {
 #[tonic::async_trait]
 impl chord_proto::chord_server::Chord for ChordService {
     
-    /// finds the next responsible node for a given position in the hash ring
+    /// finds the next responsible node for a given position in the hash ring. The response
+    /// contains the path of all nodes the lookup was forwarded to.
     async fn find_successor(
         &self,
         request: Request<chord_proto::HashPosMsg>,
-    ) -> Result<Response<chord_proto::AddressMsg>, Status> {
+    ) -> Result<Response<FindSuccessorResponse>, Status> {
         let key: HashPos = request.into_inner().into();
 
         let direct_successor_address = self.get_successor_address().await;
@@ -159,39 +579,150 @@ impl chord_proto::chord_server::Chord for ChordService {
             key: key.to_be_bytes().to_vec()
         };
 
-        let successor_address_msg: AddressMsg = if is_between(key, self.pos + 1, successor_pos, false, false) {
-            direct_successor_address.into()
+        let mut response = if key == self.pos {
+            // no finger precedes the node's own position, so it must not be forwarded. The
+            // position of a drained node belongs to its successor.
+            FindSuccessorResponse {
+                address: if self.is_drained() { direct_successor_address } else { self.address.clone() },
+                hop_count: 0,
+                path: Vec::new(),
+            }
+        } else if is_between(key, self.pos + 1, successor_pos, false, false) {
+            FindSuccessorResponse {
+                address: direct_successor_address,
+                hop_count: 0,
+                path: Vec::new(),
+            }
         } else {
-            let closest_preceding_node_address = self.find_closest_preceding_finger(Request::new(key_pos_msg.clone()))
+            let closest_preceding_finger_address: Address = self.find_closest_preceding_finger(Request::new(key_pos_msg.clone()))
                 .await
-                .unwrap().into_inner();
-
-            match connect_with_retry(&closest_preceding_node_address.address).await {
-                Ok(mut closest_preceding_node_client) => {
-                    closest_preceding_node_client.find_successor(Request::new(key.into()))
-                        .await?
-                        .into_inner()
+                .unwrap().into_inner()
+                .address;
+            // if every finger preceding the key is suspected, the closest preceding finger is this
+            // node itself, which must not forward the lookup to itself
+            let closest_preceding_node_address_option = if closest_preceding_finger_address.eq(&self.address) {
+                self.find_closest_preceding_successor(key)
+            } else {
+                Some(closest_preceding_finger_address)
+            };
+            match closest_preceding_node_address_option {
+                None => {
+                    warn!("No live node precedes {}, answering with the direct successor {}", key, direct_successor_address);
+                    FindSuccessorResponse {
+                        address: direct_successor_address,
+                        hop_count: 0,
+                        path: Vec::new(),
+                    }
                 }
-                Err(status) => {
-                    // if node returned by closest_preceding_node_address is unavailable, delegate find_successor call to predecessor
-                    let mut counter = 0;
-                    loop {
-                        if let Some(mut predecessor_client) = self.get_predecessor_client().await {
-                            return predecessor_client.find_successor(Request::new(key.into())).await;
-                        }
-                        if counter > 20 {
-                            return Err(status);
+                Some(closest_preceding_node_address) => {
+                    // a pooled channel may have broken since it was pooled, so besides the connect,
+                    // the call itself may fail if the node is gone
+                    let response_result = match connect_with_retry(&closest_preceding_node_address).await {
+                        Ok(mut closest_preceding_node_client) => closest_preceding_node_client.find_successor(Request::new(key.into())).await,
+                        Err(status) => Err(status)
+                    };
+                    match response_result {
+                        Ok(response) => response.into_inner(),
+                        Err(status) => {
+                            evict_if_unavailable(&closest_preceding_node_address, &status);
+                            warn!("Closest preceding node {} failed to find the successor of {}: {}", closest_preceding_node_address, key, status.message());
+                            // if node returned by closest_preceding_node_address is unavailable, delegate find_successor call to predecessor
+                            let mut counter = 0;
+                            loop {
+                                if let Some(mut predecessor_client) = self.get_predecessor_client().await? {
+                                    break predecessor_client.find_successor(Request::new(key.into())).await?.into_inner();
+                                }
+                                if counter > 20 {
+                                    return Err(status);
+                                }
+                                counter += 1;
+                            }
                         }
-                        counter += 1;
                     }
                 }
             }
         };
+        response.path.insert(0, self.address.clone());
+        response.hop_count = response.path.len() as u32 - 1;
 
-        debug!("Received find_successor call for {:?}, successor is {:?}", key, successor_address_msg);
-        Ok(Response::new(successor_address_msg))
+        debug!("Received find_successor call for {:?}, successor is {:?} after {} hops", key, response.address, response.hop_count);
+        Ok(Response::new(response))
     }
 
+    /// finds the responsible node for a position like find_successor, but additionally returns the
+    /// path of the lookup. The request may choose the lookup mode, otherwise the configured one is used.
+    async fn lookup(&self, request: Request<LookupRequest>) -> Result<Response<LookupResponse>, Status> {
+        let request = request.into_inner();
+        let key: HashPos = request.pos.ok_or_else(|| Status::invalid_argument("Position required"))?.into();
+        let mode = match LookupMode::from_i32(request.mode) {
+            Some(LookupMode::Default) | None => self.lookup_mode,
+            Some(mode) => mode,
+        };
+
+        let start = Instant::now();
+        let (successor_address, hops) = match mode {
+            LookupMode::Iterative => self.iterative_lookup(&key).await?,
+            _ => {
+                let response = self.find_successor(Request::new(key.into()))
+                    .await?
+                    .into_inner();
+                let latency_micros = start.elapsed().as_micros() as u64;
+                let hops = response.path.into_iter()
+                    .enumerate()
+                    .map(|(i, address)| LookupHopMsg {
+                        address,
+                        latency_micros: if i == 0 { latency_micros } else { 0 },
+                    })
+                    .collect();
+                (response.address, hops)
+            }
+        };
+
+        self.lookup_stats.lock().unwrap().record(start.elapsed(), hops.len().saturating_sub(1));
+        debug!("Lookup of {:?} in mode {:?} took {} hops, successor is {}", key, mode, hops.len(), successor_address);
+        Ok(Response::new(LookupResponse {
+            successor: Some(successor_address.into()),
+            mode: mode.into(),
+            hops,
+        }))
+    }
+
+    /// returns the first `count` distinct nodes at or after the position: the responsible node
+    /// followed by its successors. Successor lists are fetched node by node until enough nodes
+    /// are known or the ring has been walked around.
+    async fn find_successors(&self, request: Request<FindSuccessorsRequest>) -> Result<Response<FindSuccessorsResponse>, Status> {
+        let request = request.into_inner();
+        let count = request.count as usize;
+        let pos_msg = request.pos.ok_or_else(|| Status::invalid_argument("Position missing"))?;
+        let mut successors: Vec<Address> = Vec::new();
+        if count > 0 {
+            successors.push(self.find_successor(Request::new(pos_msg)).await?.into_inner().into());
+        }
+
+        let mut next_index = 0;
+        while successors.len() < count && next_index < successors.len() {
+            let successor_list: SuccessorList = if successors[next_index].eq(&self.address) {
+                self.successor_list.lock().unwrap().clone()
+            } else {
+                let mut client = connect_with_retry(&successors[next_index]).await?;
+                client.get_successor_list(Request::new(Empty {})).await?.into_inner().into()
+            };
+            for successor in successor_list.successors {
+                if !successors.contains(&successor) && !successor.is_empty() {
+                    successors.push(successor);
+                }
+            }
+            next_index += 1;
+        }
+        successors.truncate(count);
+
+        Ok(Response::new(FindSuccessorsResponse {
+            successors: successors.into_iter()
+                .map(|successor| -> FingerEntry { successor.into() })
+                .map(|finger_entry| finger_entry.into())
+                .collect(),
+        }))
+    }
 
     /// returns current node's value of the predecessor handle
     async fn get_predecessor(&self, _request: Request<Empty>) -> Result<Response<GetPredecessorResponse>, Status> {
@@ -213,6 +744,14 @@ impl chord_proto::chord_server::Chord for ChordService {
         Ok(Response::new(self.successor_list.lock().unwrap().clone().into()))
     }
 
+    async fn get_finger_table(&self, _: Request<Empty>) -> Result<Response<FingerTableMsg>, Status> {
+        Ok(Response::new(self.finger_table.lock().unwrap().clone().into()))
+    }
+
+    async fn get_predecessor_list(&self, _: Request<Empty>) -> Result<Response<PredecessorListMsg>, Status> {
+        Ok(Response::new(self.predecessor_list.lock().unwrap().clone().into()))
+    }
+
 
     /// find the finger in the finger table that closest precedes the hash position given in the request
     async fn find_closest_preceding_finger(&self, request: Request<HashPosMsg>) -> Result<Response<FingerEntryMsg>, Status> {
@@ -222,6 +761,10 @@ impl chord_proto::chord_server::Chord for ChordService {
                 // ignore yet uninitialized entries
                 continue;
             }
+            if !failure_detector().lock().unwrap().is_available(&finger.address) {
+                // route around fingers that are suspected to have failed
+                continue;
+            }
             let node_pos = hash(finger.get_address().as_bytes());
             if is_between(node_pos, self.pos, key, true, true) {
                 return Ok(Response::new(FingerEntryMsg {
@@ -244,6 +787,7 @@ impl chord_proto::chord_server::Chord for ChordService {
         let finger_table_guard = self.finger_table.lock().unwrap();
         let predecessor_option = self.predecessor_option.lock().unwrap();
         let successor_list = self.successor_list.lock().unwrap();
+        let predecessor_list = self.predecessor_list.lock().unwrap();
 
         Ok(Response::new(NodeSummaryMsg {
             url: self.address.clone(),
@@ -257,6 +801,7 @@ impl chord_proto::chord_server::Chord for ChordService {
                 .map(|finger| finger.into())
                 .collect(),
             successor_list: Some(successor_list.clone().into()),
+            predecessor_list: Some(predecessor_list.clone().into()),
         }))
     }
     
@@ -278,11 +823,11 @@ impl chord_proto::chord_server::Chord for ChordService {
         let kv_pairs = {
             let one = HashPos::one();
             self.kv_store.lock().unwrap()
+                .range(one + 1, one)
                 .iter()
-                .filter(move |(key, _)| is_between(hash(*key), one + 1, one, false, false))
                 .map(|(key, value)| KvPairDebugMsg {
                     key: key.map(|b| b.to_string()).join(" "),
-                    value: value.0.clone(),
+                    value: value.value.clone(),
                 }).collect()
         };
         Ok(Response::new(GetKvStoreDataResponse { kv_pairs }))
@@ -290,63 +835,221 @@ impl chord_proto::chord_server::Chord for ChordService {
 
     /// GET operation on the key value storage 
     async fn get(&self, request: Request<GetRequest>) -> Result<Response<GetResponse>, Status> {
+        if self.is_drained() {
+            let (mut successor_client, successor_address) = self.get_client_for_forwarding().await?;
+            debug!("Node is drained, forwarding GET to {}", successor_address);
+            return successor_client.get(request.into_inner()).await;
+        }
         let key: Key = request.into_inner().key.try_into().unwrap();
-        let predecessor_pos = {
-            if let Some(finger_entry) = self.predecessor_option.lock().unwrap().clone() {
-                hash(finger_entry.address.as_bytes())
-            } else {
-                return Err(Status::internal("Predecessor not set"));
+        self.check_responsibility(&key).await?;
+
+        let (entry_option, replica_status) = self.quorum_read(&key).await;
+        if replica_status.responded < replica_status.read_quorum {
+            warn!("Degraded read for key {:?}, only {} of {} replicas responded", key, replica_status.responded, replica_status.read_quorum);
+        }
+
+        match entry_option {
+            Some(entry) if entry.tombstone => {
+                info!("Received GET request for key {:?}, but key was removed", key);
+                Ok(Response::new(GetResponse {
+                    value: Vec::default(),
+                    status: GetStatus::NotFound.into(),
+                    replica_status: Some(replica_status),
+                    version: 0,
+                }))
             }
-        };
-        if is_between(hash(&key), predecessor_pos + 1, self.pos, false, false) {
-            let mut kv_store_guard = self.kv_store.lock().unwrap();
-
-            match kv_store_guard.get(&key).cloned() {
-                Some((value, expiration_date)) => {
-                    if has_expired(&expiration_date) {
-                        let since = now().as_secs() - expiration_date;
-                        info!("Received GET request for key {:?}, but value is expired since {} seconds!", key, since);
-                        kv_store_guard.remove(&key);
-                        info!("Removed expired key {:?}", &key);
-                        return Ok(Response::new(GetResponse {
-                            value: value.clone(),
-                            status: GetStatus::Expired.into(),
-                        }));
-                    } else {
-                        info!("Received GET request for key {:?}, value is: {}", key, value);
-                        return Ok(Response::new(GetResponse {
-                            value: value.clone(),
-                            status: GetStatus::Ok.into(),
-                        }));
-                    }
-                }
-                None => {
-                    warn!("Received GET request for key {:?}, but not found)", key);
-                    return Ok(Response::new(GetResponse {
-                        value: String::default(),
-                        status: GetStatus::NotFound.into(),
-                    }));
+            Some(entry) => {
+                if has_expired(&entry.expiration_date) {
+                    let since = now().as_secs() - entry.expiration_date;
+                    info!("Received GET request for key {:?}, but value is expired since {} seconds!", key, since);
+                    self.kv_store.lock().unwrap().remove(&key);
+                    info!("Removed expired key {:?}", &key);
+                    Ok(Response::new(GetResponse {
+                        value: entry.value,
+                        status: GetStatus::Expired.into(),
+                        replica_status: Some(replica_status),
+                        version: 0,
+                    }))
+                } else {
+                    info!("Received GET request for key {:?}, value has {} bytes", key, entry.value.len());
+                    Ok(Response::new(GetResponse {
+                        value: entry.value,
+                        status: GetStatus::Ok.into(),
+                        replica_status: Some(replica_status),
+                        version: entry.version,
+                    }))
                 }
             }
-        } else {
-            error!("This node is responsible for interval ({}, {}] !", predecessor_pos, self.pos);
-            let msg = format!("Node ({}, {}) is responsible for range ({}, {}]", self.address, self.pos, predecessor_pos, self.pos);
-            return Err(Status::internal(msg));
-        };
+            None => {
+                warn!("Received GET request for key {:?}, but not found)", key);
+                Ok(Response::new(GetResponse {
+                    value: Vec::default(),
+                    status: GetStatus::NotFound.into(),
+                    replica_status: Some(replica_status),
+                    version: 0,
+                }))
+            }
+        }
     }
     
-    /// PUT operation on the key value storage 
-    async fn put(&self, request: Request<PutRequest>) -> Result<Response<Empty>, Status> {
-        let key = request.get_ref().key.clone().try_into().unwrap();
+    /// PUT operation on the key value storage. The value is additionally written to the next
+    /// `replication` nodes of the successor list.
+    async fn put(&self, request: Request<PutRequest>) -> Result<Response<PutResponse>, Status> {
+        if self.is_drained() {
+            let (mut successor_client, successor_address) = self.get_client_for_forwarding().await?;
+            debug!("Node is drained, forwarding PUT to {}", successor_address);
+            return successor_client.put(request.into_inner()).await;
+        }
+        let key: Key = request.get_ref().key.clone().try_into().unwrap();
         let ttl = request.get_ref().ttl;
         let replication = request.get_ref().replication;
+        let expected_version_option = request.get_ref().expected_version;
         let value = &request.get_ref().value;
+        self.check_responsibility(&key).await?;
+
+        // bring the local entry up to date, so that the version is compared against the freshest replica
+        let _ = self.quorum_read(&key).await;
+
+        let entry = {
+            let mut kv_store = self.kv_store.lock().unwrap();
+            let stored_entry_option = kv_store.get(&key);
+            let current_version = visible_version(&stored_entry_option);
+            if let Some(expected_version) = expected_version_option {
+                if expected_version != current_version {
+                    info!("Rejected PUT request for key {:?}, expected version {} but found {}", hash(&key), expected_version, current_version);
+                    return Ok(Response::new(PutResponse {
+                        status: PutStatus::VersionMismatch.into(),
+                        version: current_version,
+                    }));
+                }
+            }
+
+            let entry = KvEntry {
+                value: value.clone(),
+                expiration_date: now().as_secs() + ttl,
+                replication,
+                timestamp: now().as_millis() as u64,
+                tombstone: false,
+                version: stored_entry_option.map_or(0, |entry| entry.version) + 1,
+            };
+            match kv_store.try_put(key, entry.clone()) {
+                Ok(_) => {}
+                Err(PutError::QuotaExceeded) => {
+                    warn!("Rejected PUT request for key {:?}, storage quota exceeded", hash(&key));
+                    return Ok(Response::new(PutResponse {
+                        status: PutStatus::QuotaExceeded.into(),
+                        version: current_version,
+                    }));
+                }
+                Err(err) => return Err(Status::internal(format!("Failed to store key {:?}: {}", hash(&key), err)))
+            }
+            entry
+        };
+        info!("Received PUT request ({:?}, {} bytes) with ttl {} and replication {}, new version {}", hash(&key), value.len(), ttl, replication, entry.version);
+
+        self.replicate_entry(&key, &entry).await;
+        Ok(Response::new(PutResponse {
+            status: PutStatus::Ok.into(),
+            version: entry.version,
+        }))
+    }
+
+    /// REMOVE operation on the key value storage. Instead of deleting the pair, a tombstone is
+    /// written and replicated like a regular value, so that stale replicas can not bring back the
+    /// removed value during read-repair. Tombstones are dropped by the sweeper once they expire.
+    async fn remove(&self, request: Request<RemoveRequest>) -> Result<Response<RemoveResponse>, Status> {
+        if self.is_drained() {
+            let (mut successor_client, successor_address) = self.get_client_for_forwarding().await?;
+            debug!("Node is drained, forwarding REMOVE to {}", successor_address);
+            return successor_client.remove(request.into_inner()).await;
+        }
+        let key: Key = request.into_inner().key.try_into().unwrap();
+        self.check_responsibility(&key).await?;
+
+        let (entry_option, _) = self.quorum_read(&key).await;
+        let entry = match entry_option {
+            Some(entry) if !entry.tombstone && !has_expired(&entry.expiration_date) => entry,
+            _ => {
+                info!("Received REMOVE request for key {:?}, but not found", key);
+                return Ok(Response::new(RemoveResponse { status: RemoveStatus::NotFound.into() }));
+            }
+        };
 
-        // todo: handle replication
+        let tombstone = KvEntry {
+            value: Vec::default(),
+            expiration_date: now().as_secs() + TOMBSTONE_LIVE_TIME,
+            replication: entry.replication,
+            timestamp: now().as_millis() as u64,
+            tombstone: true,
+            version: entry.version + 1,
+        };
+        let _ = self.kv_store.lock().unwrap().put(key, tombstone.clone());
+        info!("Received REMOVE request for key {:?}, wrote tombstone", hash(&key));
+
+        self.replicate_entry(&key, &tombstone).await;
+        Ok(Response::new(RemoveResponse { status: RemoveStatus::Ok.into() }))
+    }
 
-        let expiration_date = now().as_secs() + ttl;
-        let _ = self.kv_store.lock().unwrap().insert(key, (value.clone(), expiration_date));
-        info!("Received PUT request ({:?}, {}) with ttl {} and replication {}", hash(&key), value, ttl, replication);
+    /// Stores a stream of key value pairs as replicas. The pairs are stored without checking the
+    /// responsible range, as the sending node is the one responsible for them.
+    async fn replicate(&self, request: Request<Streaming<KvPairMsg>>) -> Result<Response<Empty>, Status> {
+        let mut stream = request.into_inner();
+        let mut counter = 0;
+        while let Some(kv_msg) = stream.message().await? {
+            let (key, entry): (Key, KvEntry) = kv_msg.into();
+            self.kv_store.lock().unwrap().put(key, entry);
+            counter += 1;
+        };
+        debug!("Stored {} replicas", counter);
+        Ok(Response::new(Empty {}))
+    }
+
+    /// returns the entry stored for a key without checking the responsible range, used by the
+    /// node coordinating a quorum read
+    async fn get_replica(&self, request: Request<GetRequest>) -> Result<Response<GetReplicaResponse>, Status> {
+        let key: Key = request.into_inner().key.try_into().unwrap();
+        let pair = self.kv_store.lock().unwrap()
+            .get(&key)
+            .map(|entry| (key, entry).into());
+        Ok(Response::new(GetReplicaResponse { pair }))
+    }
+
+    /// Re-creates the replicas of all pairs this node is responsible for on the current successor
+    /// list. Called by the successor list thread whenever the successor list has changed.
+    async fn refresh_replicas(&self, _: Request<Empty>) -> Result<Response<Empty>, Status> {
+        let predecessor_pos = {
+            if let Some(finger_entry) = self.predecessor_option.lock().unwrap().clone() {
+                hash(finger_entry.address.as_bytes())
+            } else {
+                debug!("Predecessor not set, skipping replica refresh");
+                return Ok(Response::new(Empty {}));
+            }
+        };
+
+        let pairs_to_replicate: Vec<KvPairMsg> = self.kv_store.lock().unwrap()
+            .range(predecessor_pos + 1, self.pos)
+            .into_iter()
+            .filter(|(_, entry)| entry.replication > 0 && !has_expired(&entry.expiration_date))
+            .map(|pair| pair.into())
+            .collect();
+
+        let max_replication = pairs_to_replicate.iter()
+            .map(|pair| pair.replication)
+            .max()
+            .unwrap_or(0);
+
+        // the i-th successor receives all pairs that need more than i replicas
+        for (i, replica_address) in self.get_replica_addresses(max_replication).await.iter().enumerate() {
+            let replicas: Vec<KvPairMsg> = pairs_to_replicate.iter()
+                .filter(|pair| pair.replication as usize > i)
+                .cloned()
+                .collect();
+            let replica_count = replicas.len();
+            match send_replicas(replica_address, replicas).await {
+                Ok(_) => info!("Refreshed {} replicas on {}", replica_count, replica_address),
+                Err(e) => warn!("Failed to refresh replicas on {}: {}", replica_address, e)
+            }
+        }
         Ok(Response::new(Empty {}))
     }
     
@@ -364,9 +1067,18 @@ impl chord_proto::chord_server::Chord for ChordService {
 
         match responsible_node_for_lookup_pos_response_result {
             Ok(responsible_node_for_lookup_pos_response) => {
-                let responsible_node_address: Address = responsible_node_for_lookup_pos_response.into_inner().into();
+                let mut responsible_node_address: Address = responsible_node_for_lookup_pos_response.into_inner().into();
                 if index == 1 {
                     self.successor_list.lock().unwrap().successors[0] = responsible_node_address.clone();
+                } else if index > 1 {
+                    // the first fingers have to point to the direct successor, all others may
+                    // point to any node within their interval
+                    let interval_end = if index + 1 < HashPos::finger_count() {
+                        self.pos.overflowing_add(HashPos::one().overflowing_shl(index as u32 + 1).0).0
+                    } else {
+                        self.pos
+                    };
+                    responsible_node_address = self.select_closest_finger(responsible_node_address, lookup_position, interval_end).await;
                 }
                 *self.fix_finger_index.lock().unwrap() = index;
                 self.finger_table.lock().unwrap().fingers[index].address = responsible_node_address;
@@ -378,13 +1090,26 @@ impl chord_proto::chord_server::Chord for ChordService {
 
     /// updates the successor list and calls notify on the successor
     async fn stabilize(&self, _: Request<Empty>) -> Result<Response<Empty>, Status> {
+        if self.is_drained() {
+            debug!("Node is drained, skipping stabilization");
+            return Ok(Response::new(Empty {}));
+        }
         let (mut current_successor_client, current_successor_address) = self.get_client_for_closest_successor().await;
+        let direct_successor_address = self.get_successor_address().await;
+        if current_successor_address.ne(&direct_successor_address) && !failure_detector().lock().unwrap().is_available(&direct_successor_address) {
+            info!("Successor {} is suspected to have failed, replacing it with {}", direct_successor_address, current_successor_address);
+            self.set_successor(&current_successor_address).await;
+        }
         let current_successors_predecessor_address_optional: Option<Address> = current_successor_client.get_predecessor(Request::new(Empty {}))
             .await
             .unwrap().into_inner().address_optional.map(|address| address.into());
 
         if let Some(current_successors_predecessor_address) = current_successors_predecessor_address_optional {
-            if !current_successors_predecessor_address.is_empty() {
+            // the successor may still point to a predecessor that has just failed. A suspected
+            // node is probed, it may have re-joined after this node stopped hearing from it.
+            let is_available = failure_detector().lock().unwrap().is_available(&current_successors_predecessor_address);
+            if !current_successors_predecessor_address.is_empty()
+                && (is_available || connect_to_first_reachable_node(std::slice::from_ref(&current_successors_predecessor_address)).await.is_some()) {
                 let current_successors_predecessor_pos = hash(current_successors_predecessor_address.as_bytes());
                 let successor_pos = hash(current_successor_address.as_bytes());
                 if is_between(current_successors_predecessor_pos, self.pos + 1, successor_pos, false, true) {
@@ -393,8 +1118,8 @@ impl chord_proto::chord_server::Chord for ChordService {
             }
         }
 
-        let mut successor_client: ChordClient<Channel> = connect_without_retry(&self.get_successor_address().await)
-            .await;
+        let mut successor_client: ChordClient<Channel> = connect_with_retry(&self.get_successor_address().await)
+            .await?;
 
         let notify_request: NotifyRequest = NotifyRequest {
             address: Some(self.address.clone().into()),
@@ -405,9 +1130,40 @@ impl chord_proto::chord_server::Chord for ChordService {
             .await?
             .into_inner();
 
-        while let Some(pair) = data_handoff_stream.message().await.unwrap() {
-            let key: Key = pair.key.try_into().unwrap();
-            self.kv_store.lock().unwrap().insert(key, (pair.value, pair.expiration_date));
+        // the successor only removes the pairs once they are acknowledged, so an interrupted
+        // transfer is repeated with a later notify
+        let mut transferred_pairs: Vec<(Key, KvEntry)> = Vec::new();
+        while let Some(pair) = data_handoff_stream.message().await? {
+            transferred_pairs.push(pair.into());
+        }
+        if !transferred_pairs.is_empty() {
+            let mut stored_pairs: Vec<(Key, KvEntry)> = Vec::new();
+            {
+                let mut kv_store = self.kv_store.lock().unwrap();
+                for (key, entry) in transferred_pairs.iter() {
+                    // a repeated transfer must not overwrite a pair that was updated meanwhile
+                    if kv_store.get(key).is_some_and(|stored_entry| stored_entry.version > entry.version) {
+                        stored_pairs.push((*key, entry.clone()));
+                        continue;
+                    }
+                    match kv_store.try_put(*key, entry.clone()) {
+                        Ok(_) => stored_pairs.push((*key, entry.clone())),
+                        Err(err) => warn!("Failed to store transferred key {:?}: {}", hash(key), err)
+                    }
+                }
+            }
+            let is_complete = stored_pairs.len() == transferred_pairs.len();
+            let acknowledged_pairs = if is_complete { &stored_pairs } else { &transferred_pairs };
+            let transfer_ack = TransferAckMsg {
+                address: Some(self.address.clone().into()),
+                count: acknowledged_pairs.len() as u64,
+                checksum: transfer_checksum(acknowledged_pairs),
+                rejected: !is_complete,
+            };
+            if !is_complete {
+                warn!("Stored {} of {} transferred pairs, rejecting the transfer", stored_pairs.len(), transferred_pairs.len());
+            }
+            successor_client.acknowledge_transfer(Request::new(transfer_ack)).await?;
         }
 
         Ok(Response::new(Empty {}))
@@ -426,8 +1182,8 @@ impl chord_proto::chord_server::Chord for ChordService {
         let pow_token_msg: PowTokenMsg = notify_request.pow_token.unwrap();
         let pow_token: PowToken = pow_token_msg.into();
 
-        let (has_expired, valid) = pow_token.validate();
-        if has_expired {
+        let (token_expired, valid) = pow_token.validate();
+        if token_expired {
             return Err(Status::cancelled("Pow token expired"))
         }
         if !valid {
@@ -441,6 +1197,8 @@ impl chord_proto::chord_server::Chord for ChordService {
         let mut predecessor_option_guard = self.predecessor_option.lock().unwrap();
 
         let (update_predecessor_to_caller, lower, upper) = match *predecessor_option_guard {
+            // a drained node does not accept a predecessor, as it must not own a range
+            _ if self.is_drained() => (false, HashPos::default(), HashPos::default()),
             Some(ref prev_predecessor) => {
                 let lower = hash(prev_predecessor.address.as_bytes());
                 let upper = self.pos;
@@ -460,38 +1218,48 @@ impl chord_proto::chord_server::Chord for ChordService {
                 key: caller_pos,
                 address: caller_address.clone(),
             });
+            lookup_cache().lock().unwrap().clear();
             debug!("Updated predecessor due to notify-call");
         }
 
-        let kv_store_arc = self.kv_store.clone();
-        if update_predecessor_to_caller {
+        // the node does not hand data to itself while it is alone in the ring. A transfer to the
+        // caller that has not been acknowledged in time is repeated.
+        let transfer_range_option = if update_predecessor_to_caller && caller_address.ne(&self.address) {
+            Some((lower, upper))
+        } else {
+            let retry_range_option = self.pending_transfers.lock().unwrap()
+                .next_retry(caller_address, Duration::from_millis(TRANSFER_ACK_TIMEOUT_MILLIS));
+            if let Some((lower, upper)) = retry_range_option {
+                warn!("Transfer of ({}, {}] to {} was not acknowledged, repeating it", lower, upper, caller_address);
+            }
+            retry_range_option
+        };
+        drop(predecessor_option_guard);
+
+        if let Some((lower, upper)) = transfer_range_option {
+            let kv_store_arc = self.kv_store.clone();
+            let pending_transfers_arc = self.pending_transfers.clone();
+            let caller_address = caller_address.clone();
             tokio::spawn(async move {
                 info!("Handing over data from ({}, {}]", lower, upper);
 
-                let kv_store_lock_result = kv_store_arc.lock();
-                let mut kv_store_lock = kv_store_lock_result.unwrap();
-
-                let pairs_to_handoff: Vec<(Vec<u8>, String, ExpirationDate)> = kv_store_lock
-                    .iter()
-                    .filter(|(key, _)| is_between(hash(*key), lower, upper, false, false))
-                    .map(|(key, (value, ttl))| (key.to_vec(), value.clone(), ttl.clone()))
+                let pairs_to_handoff: Vec<(Key, KvEntry)> = kv_store_arc.lock().unwrap().range(lower, upper)
+                    .into_iter()
+                    .filter(|(_, entry)| !has_expired(&entry.expiration_date))
                     .collect();
 
-                for (key, value, expiration_date) in pairs_to_handoff.iter() {
-                    let pair = KvPairMsg {
-                        key: key.to_vec(),
-                        value: value.clone(),
-                        expiration_date: expiration_date.clone(),
-                    };
-                    debug!("Handing over KV pair ({:?}, {})", key, value);
-                    match tx.send(Ok(pair)) {
-                        Ok(_) => {
-                            let key: Key = key.clone().try_into().unwrap();
-                            kv_store_lock.remove(&key);
-                        }
-                        Err(err) => {
-                            error!("ERROR: failed to update stream client: {:?}", err)
-                        }
+                // the pairs are removed once the caller acknowledges them
+                if pairs_to_handoff.is_empty() {
+                    pending_transfers_arc.lock().unwrap().remove(&caller_address, lower, upper);
+                } else {
+                    pending_transfers_arc.lock().unwrap().insert(PendingTransfer::new(&caller_address, lower, upper, &pairs_to_handoff));
+                }
+
+                for (key, entry) in pairs_to_handoff.iter() {
+                    let pair: KvPairMsg = (key, entry).into();
+                    debug!("Handing over KV pair ({:?}, {} bytes)", key, entry.value.len());
+                    if let Err(err) = tx.send(Ok(pair)) {
+                        error!("ERROR: failed to update stream client: {:?}", err)
                     }
                 }
                 info!("Data handoff finished, transferred {} pairs", pairs_to_handoff.len())
@@ -502,6 +1270,43 @@ impl chord_proto::chord_server::Chord for ChordService {
         Ok(Response::new(Box::pin(stream) as Self::NotifyStream))
     }
 
+    /// Called by the new predecessor once it has stored the pairs streamed by notify. The pairs
+    /// are only removed if count and checksum match a pending transfer to that node. If the
+    /// predecessor rejects the transfer or does not acknowledge it, the range is transferred again
+    /// with a later notify.
+    async fn acknowledge_transfer(&self, request: Request<TransferAckMsg>) -> Result<Response<Empty>, Status> {
+        let transfer_ack = request.into_inner();
+        let address: Address = transfer_ack.address
+            .ok_or_else(|| Status::invalid_argument("Address missing"))?
+            .into();
+
+        let pending_transfer_option = {
+            let mut pending_transfers = self.pending_transfers.lock().unwrap();
+            if transfer_ack.rejected {
+                pending_transfers.get(&address, transfer_ack.count, transfer_ack.checksum).cloned()
+            } else {
+                pending_transfers.take(&address, transfer_ack.count, transfer_ack.checksum)
+            }
+        };
+        let pending_transfer = pending_transfer_option.ok_or_else(|| Status::failed_precondition(format!(
+            "No pending transfer of {} pairs with checksum {} to {}", transfer_ack.count, transfer_ack.checksum, address)))?;
+        if transfer_ack.rejected {
+            warn!("{} could not store the transfer of ({}, {}], keeping the pairs", address, pending_transfer.lower, pending_transfer.upper);
+            return Ok(Response::new(Empty {}));
+        }
+
+        let mut kv_store = self.kv_store.lock().unwrap();
+        let mut removed = 0;
+        for (key, version) in pending_transfer.versions.iter() {
+            if kv_store.get(key).is_some_and(|entry| entry.version == *version) {
+                kv_store.remove(key);
+                removed += 1;
+            }
+        }
+        info!("{} acknowledged the transfer of {} pairs, removed {} of them", address, pending_transfer.versions.len(), removed);
+        Ok(Response::new(Empty {}))
+    }
+
     /// Receives a stream of key value pairs in a stream. Nodes that are about to shut down use this
     /// call to send their data to their successor, as the successor will be responsible for this 
     /// area in the hash ring.
@@ -510,8 +1315,13 @@ impl chord_proto::chord_server::Chord for ChordService {
         let mut counter = 0;
         info!("Receiving handoff data from predecessor!");
         while let Some(kv_msg) = stream.message().await? {
-            let key: Key = kv_msg.key.try_into().unwrap();
-            self.kv_store.lock().unwrap().insert(key, (kv_msg.value, kv_msg.expiration_date));
+            let (key, entry): (Key, KvEntry) = kv_msg.into();
+            let mut kv_store = self.kv_store.lock().unwrap();
+            // the node may already hold a newer version, e.g. written by a client while the
+            // predecessor was drained
+            if kv_store.get(&key).is_none_or(|stored_entry| (stored_entry.version, stored_entry.timestamp) < (entry.version, entry.timestamp)) {
+                kv_store.put(key, entry);
+            }
             debug!("Received kv-pair!");
             counter += 1;
         };
@@ -519,9 +1329,176 @@ impl chord_proto::chord_server::Chord for ChordService {
         Ok(Response::new(Empty {}))
     }
 
-    /// dummy call, used to check if the receiver node is still available.
+    /// Called by a neighbour that leaves the ring. If it is the predecessor, its own predecessor
+    /// takes its place. If it is the direct successor, its successor list replaces this node's
+    /// list. The response acknowledges that the ring is repaired on this side.
+    async fn leave(&self, request: Request<LeaveRequest>) -> Result<Response<Empty>, Status> {
+        let leave_request = request.into_inner();
+        let leaving_address: Address = leave_request.address
+            .ok_or_else(|| Status::invalid_argument("Missing address of the leaving node"))?
+            .into();
+        let mut range_changed = false;
+
+        let is_predecessor = matches!(*self.predecessor_option.lock().unwrap(), Some(ref predecessor) if predecessor.address.eq(&leaving_address));
+        if is_predecessor {
+            let new_predecessor_option: Option<Address> = leave_request.predecessor
+                .map(|address| address.into())
+                .filter(|address: &Address| !address.is_empty() && address.ne(&leaving_address));
+            info!("Predecessor {} is leaving, new predecessor is {:?}", leaving_address, new_predecessor_option);
+            *self.predecessor_option.lock().unwrap() = new_predecessor_option.map(|address| FingerEntry {
+                key: hash(address.as_bytes()),
+                address,
+            });
+            self.predecessor_list.lock().unwrap().predecessors.retain(|predecessor| predecessor.ne(&leaving_address));
+            lookup_cache().lock().unwrap().clear();
+            range_changed = true;
+        }
+
+        if self.get_successor_address().await.eq(&leaving_address) {
+            let leaving_successor_list: SuccessorList = leave_request.successor_list
+                .ok_or_else(|| Status::invalid_argument("Missing successor list of the leaving node"))?
+                .into();
+            let mut successors = leaving_successor_list.successors.into_iter()
+                .filter(|successor| successor.ne(&leaving_address));
+            let new_successor_address = successors.next().unwrap_or(self.address.clone());
+            info!("Successor {} is leaving, new successor is {}", leaving_address, new_successor_address);
+            self.set_successor(&new_successor_address).await;
+            {
+                self.successor_list.lock().unwrap().update_with_other_succ_list(SuccessorList {
+                    own_address: new_successor_address.clone(),
+                    successors: successors.collect(),
+                }, self.successor_list_size);
+            }
+            // the range of the leaving node now belongs to the new successor
+            for finger in self.finger_table.lock().unwrap().fingers.iter_mut()
+                .filter(|finger| finger.address.eq(&leaving_address)) {
+                finger.address = new_successor_address.clone();
+            }
+            range_changed = true;
+        }
+
+        if range_changed {
+            self.refresh_replicas(Request::new(Empty {})).await?;
+        }
+        Ok(Response::new(Empty {}))
+    }
+
+    /// Hands the whole range of this node to its successor and leaves the ring without shutting
+    /// down. While drained, the node keeps forwarding lookups and client requests, but does not
+    /// accept a predecessor in notify (requires dev_mode = true).
+    async fn drain(&self, _: Request<Empty>) -> Result<Response<Empty>, Status> {
+        if !self.dev_mode {
+            return Err(Status::unimplemented(DEBUG_RPCS_UNAVAILABLE_ERROR_MESSAGE))
+        }
+        {
+            let mut drained_guard = self.drained.lock().unwrap();
+            if *drained_guard {
+                return Err(Status::failed_precondition(format!("Node {} {} already", self.address, DRAINED_ERROR_MESSAGE)));
+            }
+            *drained_guard = true;
+        }
+        info!("Draining node, handing over its range");
+        let successor_list = self.successor_list.lock().unwrap().clone();
+        let predecessor_option = self.predecessor_option.lock().unwrap().clone()
+            .map(|predecessor| predecessor.address);
+        if hand_off_and_leave(&self.address, &self.kv_store, successor_list, predecessor_option, &[]).await.is_none() {
+            *self.drained.lock().unwrap() = false;
+            return Err(Status::unavailable("No successor reachable to take over the range"));
+        }
+        *self.predecessor_option.lock().unwrap() = None;
+        self.predecessor_list.lock().unwrap().predecessors.clear();
+        lookup_cache().lock().unwrap().clear();
+        info!("Node is drained");
+        Ok(Response::new(Empty {}))
+    }
+
+    /// Ends the maintenance of a drained node. The node re-joins the ring with the next
+    /// stabilization, which notifies its successor and receives its range back (requires
+    /// dev_mode = true).
+    async fn undrain(&self, _: Request<Empty>) -> Result<Response<Empty>, Status> {
+        if !self.dev_mode {
+            return Err(Status::unimplemented(DEBUG_RPCS_UNAVAILABLE_ERROR_MESSAGE))
+        }
+        let mut drained_guard = self.drained.lock().unwrap();
+        if !*drained_guard {
+            return Err(Status::failed_precondition(format!("Node {} is not drained", self.address)));
+        }
+        *drained_guard = false;
+        info!("Node is no longer drained, re-joining the ring");
+        Ok(Response::new(Empty {}))
+    }
+
+    /// dummy call, used to check if the receiver node is still available. A drained node reports
+    /// itself unavailable, so that the probes of the other nodes keep it out of their lists.
     async fn health(&self, _: Request<Empty>) -> Result<Response<Empty>, Status> {
+        if self.is_drained() {
+            return Err(Status::unavailable(format!("Node {} {}", self.address, DRAINED_ERROR_MESSAGE)));
+        }
         Ok(Response::new(Empty {}))
     }
 }
 
+
+
+#[cfg(test)]
+mod tests {
+    use log::LevelFilter;
+    use tokio::sync::oneshot;
+
+    use chord::utils::config::Bootstrap;
+
+    use crate::node::memory_storage::MemoryStorage;
+    use crate::threads::chord::chord_proto::chord_server::Chord;
+
+    use super::*;
+
+    async fn chord_service(address: &Address, successor_address: &Address) -> ChordService {
+        let config = Config {
+            api_address: Address::default(),
+            p2p_address: address.clone(),
+            web_address: Address::default(),
+            bootstrap: Bootstrap::default(),
+            pow_difficulty: 0,
+            log_level_filter: LevelFilter::Off,
+            dev_mode: false,
+            read_quorum: 1,
+            data_dir: None,
+            quota: Default::default(),
+            lookup_mode: Default::default(),
+            weight: 1,
+            successor_list_size: 3,
+        };
+        let mut finger_table = FingerTable::new(&hash(address.as_bytes()));
+        for finger in finger_table.fingers.iter_mut() {
+            finger.address = successor_address.clone();
+        }
+        let kv_store_arc: Arc<Mutex<KvStore>> = Arc::new(Mutex::new(MemoryStorage::new()));
+        let (tx, rx) = oneshot::channel();
+        tx.send((
+            Arc::new(Mutex::new(finger_table)),
+            Arc::new(Mutex::new(None)),
+            kv_store_arc,
+            Arc::new(Mutex::new(SuccessorList::new(address, successor_address))),
+            Arc::new(Mutex::new(PredecessorList::new(address))),
+        )).unwrap();
+        ChordService::new(rx, address, &config, Arc::new(Mutex::new(LookupStats::default()))).await
+    }
+
+    #[tokio::test]
+    async fn test_lookup_is_not_forwarded_to_itself_if_all_fingers_are_suspected() {
+        let address: Address = "127.0.0.1:15901".to_string();
+        let successor_address: Address = "127.0.0.1:15902".to_string();
+        let service = chord_service(&address, &successor_address).await;
+
+        failure_detector().lock().unwrap().missed_heartbeat(&successor_address);
+        failure_detector().lock().unwrap().missed_heartbeat(&successor_address);
+        sleep(Duration::from_secs(1)).await;
+        assert!(!failure_detector().lock().unwrap().is_available(&successor_address));
+
+        // the position right after the successor is preceded by the successor only
+        let key = hash(successor_address.as_bytes()).wrapping_add(1);
+        let response = service.find_successor(Request::new(key.into())).await.unwrap().into_inner();
+        assert_eq!(response.address, successor_address);
+        assert_eq!(response.path, vec![address]);
+    }
+}
@@ -1,19 +1,22 @@
 use std::error::Error;
+use std::future::Future;
 use std::io::ErrorKind;
 use std::mem;
 
-use log::info;
+use log::{debug, info, warn};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
-use tonic::Request;
+use tonic::{Request, Response, Status};
 use tonic::transport::Channel;
 
-use crate::threads::chord::chord_proto::{GetRequest, GetStatus, HashPosMsg, PutRequest};
+use crate::node::lookup_cache::lookup_cache;
+
+use crate::threads::chord::chord_proto::{GetRequest, GetStatus, LookupMode, LookupRequest, LookupResponse, PutRequest, PutStatus, RemoveRequest, RemoveStatus};
 use crate::threads::chord::chord_proto::chord_client::ChordClient;
-use crate::threads::chord::connect_with_retry;
-use crate::utils::constants::{DHT_FAILURE, DHT_GET, DHT_PUT, DHT_SUCCESS};
+use crate::threads::chord::{connect, connect_with_retry, evict_if_unavailable, is_connection_error};
+use crate::utils::constants::{DHT_FAILURE, DHT_GET, DHT_PUT, DHT_QUOTA_EXCEEDED, DHT_REMOVE, DHT_SUCCESS, DHT_SUCCESS_DEGRADED, DRAINED_ERROR_MESSAGE, NOT_RESPONSIBLE_ERROR_MESSAGE};
 use crate::utils::crypto;
-use crate::utils::types::HashPos;
+use crate::utils::types::{Address, HashPos};
 
 pub async fn handle_client_connection(mut socket: TcpStream, grpc_address: &String) -> Result<(), Box<dyn Error>> {
     loop {
@@ -30,6 +33,7 @@ pub async fn handle_client_connection(mut socket: TcpStream, grpc_address: &Stri
         match code {
             code if code == DHT_PUT => handle_put(&grpc_address, &mut socket, size).await,
             code if code == DHT_GET => handle_get(&grpc_address, &mut socket).await,
+            code if code == DHT_REMOVE => handle_remove(grpc_address, &mut socket).await,
             _ => panic!("invalid code {}", code)
         }.unwrap();
     }
@@ -41,18 +45,27 @@ async fn handle_get(grpc_address: &String, socket: &mut TcpStream) -> Result<(),
     socket.read_exact(&mut key_array).await?;
     info!("Processing GET for key {:?}", key_array);
 
-    let mut responsible_node_client = perform_chord_look_up(
-        &crypto::hash(key_array.as_slice()),
-        grpc_address.as_str(),
-    ).await;
+    let response = call_responsible_node(&crypto::hash(key_array.as_slice()), grpc_address.as_str(), |mut client| async move {
+        client.get(Request::new(GetRequest {
+            key: key_array.to_vec(),
+        })).await
+    }).await.unwrap();
 
-    let response = responsible_node_client.get(Request::new(GetRequest {
-        key: key_array.to_vec(),
-    })).await.unwrap();
+    let degraded = match &response.get_ref().replica_status {
+        Some(replica_status) if replica_status.responded < replica_status.read_quorum => {
+            warn!("Degraded read for key {:?}, only {} of {} replicas responded",
+                key_array, replica_status.responded, replica_status.read_quorum);
+            true
+        }
+        _ => false
+    };
 
     match GetStatus::from_i32(response.get_ref().status) {
+        Some(GetStatus::Ok) if degraded => {
+            send_dht_success_degraded(socket, key_array, response.get_ref().value.clone()).await?;
+        }
         Some(GetStatus::Ok) => {
-            send_dht_success(socket, key_array, response.get_ref().value.as_bytes().to_vec()).await?;
+            send_dht_success(socket, key_array, response.get_ref().value.clone()).await?;
         }
         Some(GetStatus::NotFound) => {
             send_dht_failure(socket, key_array).await?;
@@ -66,6 +79,30 @@ async fn handle_get(grpc_address: &String, socket: &mut TcpStream) -> Result<(),
     Ok(())
 }
 
+async fn handle_remove(grpc_address: &str, socket: &mut TcpStream) -> Result<(), Box<dyn Error>> {
+    let mut key_array: [u8; 32] = [0; 32];
+    socket.read_exact(&mut key_array).await?;
+    info!("Processing REMOVE for key {:?}", key_array);
+
+    let response = call_responsible_node(&crypto::hash(key_array.as_slice()), grpc_address, |mut client| async move {
+        client.remove(Request::new(RemoveRequest {
+            key: key_array.to_vec(),
+        })).await
+    }).await.unwrap();
+
+    match RemoveStatus::from_i32(response.get_ref().status) {
+        Some(RemoveStatus::Ok) => {
+            send_dht_success(socket, key_array, Vec::new()).await?;
+        }
+        Some(RemoveStatus::NotFound) => {
+            send_dht_failure(socket, key_array).await?;
+        }
+        _ => panic!("Received invalid remove response status")
+    }
+
+    Ok(())
+}
+
 async fn handle_put(grpc_address: &String, socket: &mut TcpStream, size: u16) -> Result<(), Box<dyn Error>> {
     let mut ttl = socket.read_u16().await.unwrap();
     if ttl == 0 {
@@ -86,39 +123,90 @@ async fn handle_put(grpc_address: &String, socket: &mut TcpStream, size: u16) ->
         - mem::size_of_val(&_reserved)
         - mem::size_of_val(&key_array);
 
-    let mut value_string = String::new();
+    let mut value = vec![0u8; remaining_msg_len];
 
-    if socket.read_to_string(&mut value_string).await.unwrap() == remaining_msg_len {
+    if socket.read_exact(&mut value).await? == remaining_msg_len {
         info!("Processing PUT for key {}...", hash_ring_pos);
 
-        let mut responsible_node_client = perform_chord_look_up(&hash_ring_pos, grpc_address.as_str())
-            .await;
-
-        let _ = responsible_node_client.put(Request::new(PutRequest {
-            key: key_array.to_vec(),
-            ttl: ttl as u64,
-            replication: replication as u32,
-            value: value_string,
-        })).await.unwrap();
+        let response = call_responsible_node(&hash_ring_pos, grpc_address.as_str(), |mut client| {
+            let value = value.clone();
+            async move {
+                client.put(Request::new(PutRequest {
+                    key: key_array.to_vec(),
+                    ttl: ttl as u64,
+                    replication: replication as u32,
+                    value,
+                    expected_version: None,
+                })).await
+            }
+        }).await.unwrap();
 
+        if let Some(PutStatus::QuotaExceeded) = PutStatus::from_i32(response.get_ref().status) {
+            warn!("PUT for key {} was rejected, storage quota exceeded", hash_ring_pos);
+            send_dht_quota_exceeded(socket, key_array).await?;
+        }
         Ok(())
     } else {
-        panic!("Error reading string value")
+        panic!("Error reading value")
+    }
+}
+
+/// returns a client for the node responsible for the key and its address. The lookup is skipped
+/// if the lookup cache covers the key.
+pub async fn perform_chord_look_up(key: &HashPos, grpc_address: &str) -> (ChordClient<Channel>, Address) {
+    let cached_address_option = lookup_cache().lock().unwrap().get(key);
+    if let Some(cached_address) = cached_address_option {
+        match connect(&cached_address).await {
+            Ok(client) => {
+                debug!("Lookup of {} answered from cache: {}", key, cached_address);
+                return (client, cached_address);
+            }
+            Err(_) => lookup_cache().lock().unwrap().invalidate(&cached_address)
+        }
     }
+    let (client, response) = perform_chord_look_up_with_path(key, grpc_address).await;
+    (client, response.successor.unwrap().into())
 }
 
-pub async fn perform_chord_look_up(key: &HashPos, grpc_address: &str) -> ChordClient<Channel> {
+/// sends a request to the node responsible for the key. If the node is unreachable or rejects
+/// the request because it is no longer responsible for the key, its cached range is dropped and
+/// the request is retried once after a fresh lookup.
+pub async fn call_responsible_node<T, F, Fut>(key: &HashPos, grpc_address: &str, call: F) -> Result<Response<T>, Status>
+    where F: Fn(ChordClient<Channel>) -> Fut, Fut: Future<Output=Result<Response<T>, Status>> {
+    let (client, address) = perform_chord_look_up(key, grpc_address).await;
+    match call(client).await {
+        Err(status) if is_connection_error(&status) || status.message().contains(NOT_RESPONSIBLE_ERROR_MESSAGE) || status.message().contains(DRAINED_ERROR_MESSAGE) => {
+            info!("{} cannot serve key {}, retrying with fresh lookup: {}", address, key, status.message());
+            evict_if_unavailable(&address, &status);
+            lookup_cache().lock().unwrap().invalidate(&address);
+            let (client, _) = perform_chord_look_up_with_path(key, grpc_address).await;
+            call(client).await
+        }
+        result => result
+    }
+}
+
+/// performs a lookup without consulting the lookup cache and returns the lookup response with
+/// the path, the responsible node is added to the cache
+pub async fn perform_chord_look_up_with_path(key: &HashPos, grpc_address: &str) -> (ChordClient<Channel>, LookupResponse) {
     let mut local_node_client: ChordClient<Channel> = connect_with_retry(&grpc_address.to_string())
         .await
         .unwrap();
 
-    // todo: retry find_sucessor if error
-    let response = local_node_client.find_successor(Request::new(HashPosMsg {
-        key: key.to_be_bytes().to_vec()
-    })).await.unwrap();
+    // todo: retry lookup if error
+    let response = local_node_client.lookup(Request::new(LookupRequest {
+        pos: Some(key.into()),
+        mode: LookupMode::Default.into(),
+    })).await.unwrap().into_inner();
 
-    let responsible_node_address = &response.get_ref().address;
-    connect_with_retry(responsible_node_address).await.unwrap()
+    let path: Vec<String> = response.hops.iter()
+        .map(|hop| format!("{} ({} us)", hop.address, hop.latency_micros))
+        .collect();
+    debug!("Lookup of {} took {} hops: {}", key, response.hops.len(), path.join(" -> "));
+
+    let responsible_node_address: Address = response.successor.clone().unwrap().into();
+    lookup_cache().lock().unwrap().insert(key, &responsible_node_address);
+    (connect_with_retry(&responsible_node_address).await.unwrap(), response)
 }
 
 async fn send_dht_success(socket: &mut TcpStream, key: [u8; 32], value: Vec<u8>) -> Result<(), Box<dyn Error>> {
@@ -134,6 +222,21 @@ async fn send_dht_success(socket: &mut TcpStream, key: [u8; 32], value: Vec<u8>)
     Ok(())
 }
 
+/// sent instead of a DHT_SUCCESS if fewer replicas than the read quorum responded to a get, so the
+/// value might not be the latest one
+async fn send_dht_success_degraded(socket: &mut TcpStream, key: [u8; 32], value: Vec<u8>) -> Result<(), Box<dyn Error>> {
+    let size = 36 + value.len() as u16;
+
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(&size.to_be_bytes());
+    buffer.extend_from_slice(&DHT_SUCCESS_DEGRADED.to_be_bytes());
+    buffer.extend_from_slice(&key);
+    buffer.extend_from_slice(&value);
+
+    socket.write_all(&buffer).await?;
+    Ok(())
+}
+
 async fn send_dht_failure(socket: &mut TcpStream, key: [u8; 32]) -> Result<(), Box<dyn Error>> {
     let size = 2 + 2 + 32 as u16;
 
@@ -145,3 +248,17 @@ async fn send_dht_failure(socket: &mut TcpStream, key: [u8; 32]) -> Result<(), B
     socket.write_all(&buffer).await?;
     Ok(())
 }
+
+/// sent instead of a DHT_FAILURE if a put was rejected because the storage of the responsible
+/// node is full
+async fn send_dht_quota_exceeded(socket: &mut TcpStream, key: [u8; 32]) -> Result<(), Box<dyn Error>> {
+    let size: u16 = 2 + 2 + 32;
+
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(&size.to_be_bytes());
+    buffer.extend_from_slice(&DHT_QUOTA_EXCEEDED.to_be_bytes());
+    buffer.extend_from_slice(&key);
+
+    socket.write_all(&buffer).await?;
+    Ok(())
+}
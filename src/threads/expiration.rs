@@ -0,0 +1,49 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, info};
+use serde::Serialize;
+use tokio::sync::oneshot::Receiver;
+use tokio::time::sleep;
+
+use crate::node::storage::KvStore;
+use crate::utils::constants::SWEEP_SLEEP_MILLIS;
+use crate::utils::time::now;
+
+/// Statistics of the expiration sweeper, shown on the web interface
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SweepStats {
+    pub sweeps: u64,
+    pub last_sweep: u64,
+    pub last_evicted: usize,
+    pub total_evicted: usize,
+}
+
+/// periodic expiration sweep:
+/// removes all expired key value pairs from the storage, also the ones that are never read again.
+/// A single sweeper serves the storages of all virtual nodes of the process, so the statistics
+/// describe the whole process.
+pub async fn sweep_expired_periodically(rxs: Vec<Receiver<Arc<Mutex<KvStore>>>>, sweep_stats_arc: Arc<Mutex<SweepStats>>) -> ! {
+    let mut kv_store_arcs = Vec::new();
+    for rx in rxs {
+        kv_store_arcs.push(rx.await.unwrap());
+    }
+    info!("Starting up periodic expiration sweep thread");
+    loop {
+        sleep(Duration::from_millis(SWEEP_SLEEP_MILLIS)).await;
+        let evicted: usize = kv_store_arcs.iter()
+            .map(|kv_store_arc| kv_store_arc.lock().unwrap().remove_expired())
+            .sum();
+        if evicted > 0 {
+            info!("Evicted {} expired key-value-pairs", evicted);
+        } else {
+            debug!("No expired key-value-pairs found");
+        }
+
+        let mut sweep_stats = sweep_stats_arc.lock().unwrap();
+        sweep_stats.sweeps += 1;
+        sweep_stats.last_sweep = now().as_secs();
+        sweep_stats.last_evicted = evicted;
+        sweep_stats.total_evicted += evicted;
+    }
+}
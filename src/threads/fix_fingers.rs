@@ -5,12 +5,12 @@ use tokio::time::sleep;
 use tonic::Request;
 
 use crate::threads::chord::chord_proto::Empty;
-use crate::threads::chord::connect_with_retry;
+use crate::threads::chord::connect_to_local_service;
 use crate::utils::constants::FIX_FINGERS_SLEEP_MILLIS;
 
 pub async fn fix_fingers_periodically(local_grpc_service_address: String) -> ! {
     info!("Starting up periodic fix_fingers thread");
-    let mut client = connect_with_retry(&local_grpc_service_address).await.unwrap();
+    let mut client = connect_to_local_service(&local_grpc_service_address).await;
     debug!("Successfully connected to local grpc service");
     loop {
         client.fix_fingers(Request::new(Empty {}))
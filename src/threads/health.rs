@@ -1,36 +1,53 @@
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use log::{debug, info};
+use log::{debug, info, warn};
 use tokio::sync::oneshot::Receiver;
 use tokio::time::sleep;
-use tonic::Request;
+use tonic::{Request, Status};
 
 use chord::utils::constants::HEALTH_SLEEP_MILLIS;
 
+use crate::node::failure_detector::failure_detector;
 use crate::node::finger_entry::FingerEntry;
+use crate::node::lookup_cache::lookup_cache;
+use crate::node::predecessor_list::PredecessorList;
 use crate::threads::chord::chord_proto::Empty;
-use crate::threads::chord::{connect, connect_with_retry};
+use crate::threads::setup::PredecessorState;
+use crate::threads::chord::{connect, connect_to_local_service, evict_if_unavailable, probe_suspected};
+use crate::utils::types::Address;
 
-pub async fn check_predecessor_health_periodically(local_grpc_service_address: String, rx: Receiver<Arc<Mutex<Option<FingerEntry>>>>) -> ! {
-    let predecessor_arc = rx.await.unwrap();
+/// periodic predecessor health check:
+/// fetching the predecessor's predecessor list doubles as heartbeat and keeps this node's
+/// predecessor list up to date. Once the failure detector suspects the predecessor, the next
+/// reachable node of the predecessor list becomes the new predecessor and the replicas of the
+/// extended range are refreshed.
+pub async fn check_predecessor_health_periodically(local_grpc_service_address: String, predecessor_list_size: usize, rx: Receiver<PredecessorState>) -> ! {
+    let (predecessor_arc, predecessor_list_arc) = rx.await.unwrap();
     info!("Starting up periodic predecessor health check thread");
-    let mut local_grpc_client = connect_with_retry(&local_grpc_service_address.clone()).await.unwrap();
+    let mut local_grpc_client = connect_to_local_service(&local_grpc_service_address).await;
     debug!("Connected to local grpc service");
     loop {
-        let predecessor_address_msg_optional = local_grpc_client.get_predecessor(Request::new(Empty {}))
-            .await
-            .unwrap().into_inner().address_optional;
-
-        if let Some(predecessor_address_msg) = predecessor_address_msg_optional {
-            match connect(&predecessor_address_msg.address).await {
-                Ok(mut predecessor_client) => {
-                    match predecessor_client.health(Request::new(Empty {})).await {
-                        Ok(_) => debug!("predecessor node healthy"),
-                        Err(_) => unset_predecessor(predecessor_arc.clone()).await
+        let predecessor_option = predecessor_arc.lock().unwrap().clone();
+
+        if let Some(predecessor) = predecessor_option {
+            match fetch_predecessor_list(&predecessor.address).await {
+                Some(mut predecessors_predecessor_list) => {
+                    debug!("predecessor node healthy");
+                    probe_suspected(&predecessors_predecessor_list.predecessors).await;
+                    failure_detector().lock().unwrap().retain_available(&mut predecessors_predecessor_list.predecessors);
+                    predecessor_list_arc.lock().unwrap().update_with_other_pred_list(predecessors_predecessor_list, predecessor_list_size);
+                }
+                None if failure_detector().lock().unwrap().is_available(&predecessor.address) => {
+                    debug!("Predecessor {} did not respond, but is not suspected yet", predecessor.address);
+                }
+                None => {
+                    if replace_predecessor(&predecessor.address, predecessor_arc.clone(), predecessor_list_arc.clone(), predecessor_list_size).await {
+                        if let Err(e) = local_grpc_client.refresh_replicas(Request::new(Empty {})).await {
+                            warn!("Failed to refresh replicas: {}", e);
+                        }
                     }
                 }
-                Err(_) => unset_predecessor(predecessor_arc.clone()).await
             }
         }
 
@@ -38,7 +55,55 @@ pub async fn check_predecessor_health_periodically(local_grpc_service_address: S
     }
 }
 
-async fn unset_predecessor(predecessor_arc: Arc<Mutex<Option<FingerEntry>>>) -> () {
+/// fetches the predecessor list of the given node and records the outcome in the failure detector
+async fn fetch_predecessor_list(address: &Address) -> Option<PredecessorList> {
+    let response_result = match connect(address).await {
+        Ok(mut client) => client.get_predecessor_list(Request::new(Empty {})).await,
+        Err(e) => Err(Status::unavailable(e.to_string()))
+    };
+    let mut failure_detector = failure_detector().lock().unwrap();
+    match response_result {
+        Ok(response) => {
+            failure_detector.heartbeat(address);
+            Some(response.into_inner().into())
+        }
+        Err(status) => {
+            failure_detector.missed_heartbeat(address);
+            evict_if_unavailable(address, &status);
+            None
+        }
+    }
+}
+
+/// replaces the failed predecessor with the next reachable node of the predecessor list, this node
+/// is then responsible for the failed node's range. Returns whether a new predecessor was found.
+async fn replace_predecessor(failed_address: &Address, predecessor_arc: Arc<Mutex<Option<FingerEntry>>>, predecessor_list_arc: Arc<Mutex<PredecessorList>>, predecessor_list_size: usize) -> bool {
+    let candidates: Vec<Address> = predecessor_list_arc.lock().unwrap().predecessors.iter()
+        .filter(|predecessor| predecessor.ne(&failed_address))
+        .filter(|predecessor| failure_detector().lock().unwrap().is_available(predecessor))
+        .cloned()
+        .collect();
+    lookup_cache().lock().unwrap().clear();
+
+    for candidate in candidates {
+        if let Some(candidates_predecessor_list) = fetch_predecessor_list(&candidate).await {
+            let mut predecessor_guard = predecessor_arc.lock().unwrap();
+            // a notify may have set a new predecessor in the meantime
+            if predecessor_guard.as_ref().is_some_and(|predecessor| predecessor.address.eq(failed_address)) {
+                info!("Predecessor {} unavailable, taking over its range from new predecessor {}", failed_address, candidate);
+                *predecessor_guard = Some(candidate.into());
+                predecessor_list_arc.lock().unwrap().update_with_other_pred_list(candidates_predecessor_list, predecessor_list_size);
+                return true;
+            }
+            return false;
+        }
+    }
+
     debug!("Predecessor unavailable, setting predecessor to Nil");
-    *predecessor_arc.lock().unwrap() = None;
+    let mut predecessor_guard = predecessor_arc.lock().unwrap();
+    if predecessor_guard.as_ref().is_some_and(|predecessor| predecessor.address.eq(failed_address)) {
+        *predecessor_guard = None;
+        predecessor_list_arc.lock().unwrap().predecessors.clear();
+    }
+    false
 }
@@ -7,3 +7,5 @@ pub mod stabilize;
 pub mod health;
 pub mod successor_list;
 pub mod web;
+pub mod snapshot;
+pub mod expiration;
@@ -1,55 +1,91 @@
 use std::error::Error;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use log::info;
+use log::{info, warn};
+use rand::seq::SliceRandom;
 use tokio::sync::oneshot::Sender;
-use tonic::Request;
+use tokio::time::sleep;
+use tonic::{Request, Status};
+use tonic::transport::Channel;
 
 use crate::node::finger_entry::FingerEntry;
+use crate::node::disk_storage::DiskStorage;
 use crate::node::finger_table::FingerTable;
+use crate::node::memory_storage::MemoryStorage;
+use crate::node::predecessor_list::PredecessorList;
+use crate::node::quota_storage::{QuotaStats, QuotaStorage};
+use crate::node::storage::{KvStore, Storage};
 use crate::node::successor_list::SuccessorList;
 use crate::threads::chord::chord_proto::{Empty, HashPosMsg};
+use crate::threads::chord::chord_proto::chord_client::ChordClient;
 use crate::threads::chord::connect_with_retry;
-use crate::utils::crypto::hash;
-use crate::utils::types::{Address, KvStore};
+use chord::utils::config::{Bootstrap, BootstrapFallback, BootstrapOrder, StorageQuota};
+use crate::utils::constants::{BOOTSTRAP_BACKOFF_INITIAL_MILLIS, BOOTSTRAP_BACKOFF_MAX_MILLIS, BOOTSTRAP_ROUNDS};
+use crate::utils::crypto::{hash, is_between};
+use crate::utils::types::Address;
+
+/// Data structures handed to the gRPC service: finger table, predecessor, storage, successor list
+/// and predecessor list
+pub type GrpcServiceState = (Arc<Mutex<FingerTable>>, Arc<Mutex<Option<FingerEntry>>>, Arc<Mutex<KvStore>>, Arc<Mutex<SuccessorList>>, Arc<Mutex<PredecessorList>>);
+
+/// Data structures handed to the predecessor health check: predecessor and predecessor list
+pub type PredecessorState = (Arc<Mutex<Option<FingerEntry>>>, Arc<Mutex<PredecessorList>>);
+
+/// Configuration of the node that is set up
+pub struct SetupContext {
+    pub bootstrap: Bootstrap,
+    pub own_grpc_address: Address,
+    pub data_dir_option: Option<String>,
+    pub quota: StorageQuota,
+    pub quota_stats_arc: Arc<Mutex<QuotaStats>>,
+}
+
+/// Channels through which the setup thread hands the node's data structures to the other threads
+pub struct SetupChannels {
+    pub tx_grpc_thread: Sender<GrpcServiceState>,
+    pub tx_handoff_thread: Sender<Arc<Mutex<KvStore>>>,
+    pub tx_check_predecessor: Sender<PredecessorState>,
+    pub tx_successor_list: Sender<Arc<Mutex<SuccessorList>>>,
+    pub tx_web_interface: Sender<Arc<Mutex<FingerTable>>>,
+    pub tx_snapshot: Sender<Arc<Mutex<KvStore>>>,
+    pub tx_sweeper: Sender<Arc<Mutex<KvStore>>>,
+}
 
 /// Setup function that distinguishes betwenn two scenarios:
-/// 1. The node joins an existing cluster (join_address_option is None)
-/// 2. The node starts up a new cluster (join_address_option is given)
-pub async fn setup(join_address_option: Option<Address>, own_grpc_address_str: &String,
-                   tx_grpc_thread: Sender<(Arc<Mutex<FingerTable>>, Arc<Mutex<Option<FingerEntry>>>, Arc<Mutex<KvStore>>, Arc<Mutex<SuccessorList>>)>,
-                   tx_handoff_thread: Sender<Arc<Mutex<KvStore>>>,
-                   tx_check_predecessor: Sender<Arc<Mutex<Option<FingerEntry>>>>,
-                   tx_successor_list: Sender<Arc<Mutex<SuccessorList>>>,
-                   tx_web_interface: Sender<Arc<Mutex<FingerTable>>>,
-) -> Result<(), Box<dyn Error>> {
+/// 1. The node joins an existing cluster through one of the bootstrap peers
+/// 2. The node starts up a new cluster (no bootstrap peers are configured, or none of them is
+///    reachable and the fallback is to start a new ring)
+///
+/// If a data directory is configured, the data persisted in it is restored before joining. If a
+/// storage quota is configured, the storage is wrapped to enforce it.
+pub async fn setup(context: SetupContext, channels: SetupChannels) -> Result<(), Box<dyn Error>> {
     info!("Starting up setup thread");
+    let SetupContext { bootstrap, own_grpc_address, data_dir_option, quota, quota_stats_arc } = context;
+    let own_grpc_address_str = &own_grpc_address;
     let own_id = hash(own_grpc_address_str.as_bytes());
 
     let finger_table_arc = Arc::new(Mutex::new(FingerTable::new(&own_id)));
-    let kv_store_arc = Arc::new(Mutex::new(KvStore::new()));
+    let kv_store_arc: Arc<Mutex<KvStore>> = match data_dir_option {
+        Some(data_dir) => {
+            let disk_storage = DiskStorage::open(Path::new(&data_dir))?;
+            info!("Restored {} key-value-pairs from {}", disk_storage.len(), data_dir);
+            with_quota(disk_storage, quota, quota_stats_arc)
+        }
+        None => with_quota(MemoryStorage::new(), quota, quota_stats_arc)
+    };
     let predecessor_option_arc = Arc::new(Mutex::new(None));
+    let predecessor_list_arc = Arc::new(Mutex::new(PredecessorList::new(own_grpc_address_str)));
     let mut successor_list_arc = Arc::new(Mutex::new(SuccessorList::default()));
 
-    match join_address_option {
-        Some(peer_address_str) => {
+    let successor_option = find_successor_via_bootstrap_peers(&bootstrap, own_grpc_address_str).await;
+    match successor_option {
+        Some((successor_address, mut successor_client)) => {
             info!("Joining existing cluster");
-            let mut join_peer_client = connect_with_retry(&peer_address_str)
-                .await
-                .unwrap();
-            let successor_address: Address = join_peer_client.find_successor(Request::new(HashPosMsg {
-                key: own_id.to_be_bytes().to_vec(),
-            })).await.unwrap().into_inner().into();
-
-            let mut successor_client = connect_with_retry(&successor_address)
-                .await
-                .unwrap();
-            let _: SuccessorList = successor_client.get_successor_list(Request::new(Empty {}))
-                .await
-                .unwrap().into_inner().into();
-
+            let finger_table = bootstrap_finger_table(own_grpc_address_str, &successor_address, &mut successor_client).await;
             successor_list_arc = Arc::new(Mutex::new(SuccessorList::new(own_grpc_address_str, &successor_address)));
-            finger_table_arc.lock().unwrap().fingers[0].address = successor_address;
+            *finger_table_arc.lock().unwrap() = finger_table;
         }
         None => {
             info!("Starting up a new cluster");
@@ -58,10 +94,121 @@ pub async fn setup(join_address_option: Option<Address>, own_grpc_address_str: &
         }
     };
 
-    tx_grpc_thread.send((finger_table_arc.clone(), predecessor_option_arc.clone(), kv_store_arc.clone(), successor_list_arc.clone())).unwrap();
-    tx_web_interface.send(finger_table_arc.clone()).unwrap();
-    tx_handoff_thread.send(kv_store_arc).unwrap();
-    tx_check_predecessor.send(predecessor_option_arc).unwrap();
-    tx_successor_list.send(successor_list_arc).unwrap();
+    channels.tx_grpc_thread.send((finger_table_arc.clone(), predecessor_option_arc.clone(), kv_store_arc.clone(), successor_list_arc.clone(), predecessor_list_arc.clone())).unwrap();
+    channels.tx_web_interface.send(finger_table_arc.clone()).unwrap();
+    channels.tx_handoff_thread.send(kv_store_arc.clone()).unwrap();
+    channels.tx_snapshot.send(kv_store_arc.clone()).unwrap();
+    channels.tx_sweeper.send(kv_store_arc).unwrap();
+    channels.tx_check_predecessor.send((predecessor_option_arc, predecessor_list_arc)).unwrap();
+    channels.tx_successor_list.send(successor_list_arc).unwrap();
     Ok(())
 }
+
+/// Tries the bootstrap peers in the configured order until one of them finds the successor of
+/// this node. After every round in which no peer could be used, the node waits with exponential
+/// backoff. Returns None if there are no peers other than this node, or if the fallback is to
+/// start a new ring and all rounds have failed.
+async fn find_successor_via_bootstrap_peers(bootstrap: &Bootstrap, own_address: &Address) -> Option<(Address, ChordClient<Channel>)> {
+    let mut peers: Vec<Address> = bootstrap.peers.iter()
+        .filter(|peer| peer.ne(&own_address))
+        .cloned()
+        .collect();
+    if peers.is_empty() {
+        return None;
+    }
+    let mut backoff = Duration::from_millis(BOOTSTRAP_BACKOFF_INITIAL_MILLIS);
+    let mut round = 0;
+    loop {
+        if bootstrap.order == BootstrapOrder::Random {
+            peers.shuffle(&mut rand::thread_rng());
+        }
+        for peer in peers.iter() {
+            match find_successor_via_peer(peer, own_address).await {
+                Ok(successor) => return Some(successor),
+                Err(e) => warn!("Bootstrap peer {} could not be used: {}", peer, e)
+            }
+        }
+
+        round += 1;
+        if bootstrap.fallback == BootstrapFallback::NewRing && round >= BOOTSTRAP_ROUNDS {
+            warn!("None of the bootstrap peers is reachable, starting a new ring");
+            return None;
+        }
+        warn!("None of the bootstrap peers is reachable, retrying in {} ms", backoff.as_millis());
+        sleep(backoff).await;
+        backoff = (backoff * 2).min(Duration::from_millis(BOOTSTRAP_BACKOFF_MAX_MILLIS));
+    }
+}
+
+/// asks the peer for the successor of this node and returns a client for the successor
+async fn find_successor_via_peer(peer_address: &Address, own_address: &Address) -> Result<(Address, ChordClient<Channel>), Status> {
+    let mut peer_client = connect_with_retry(peer_address).await?;
+    let successor_address: Address = peer_client.find_successor(Request::new(HashPosMsg {
+        key: hash(own_address.as_bytes()).to_be_bytes().to_vec(),
+    })).await?.into_inner().into();
+
+    let mut successor_client = connect_with_retry(&successor_address).await?;
+    let _: SuccessorList = successor_client.get_successor_list(Request::new(Empty {}))
+        .await?
+        .into_inner().into();
+    Ok((successor_address, successor_client))
+}
+
+/// Fills the whole finger table before the node enters the ring, instead of one finger per
+/// fix_fingers tick. The successor's fingers serve as a starting point, then every finger is
+/// looked up via the successor. Consecutive fingers whose start lies before the node found for a
+/// previous finger point to the same node, so they are filled without another lookup.
+async fn bootstrap_finger_table(own_address: &Address, successor_address: &Address, successor_client: &mut ChordClient<Channel>) -> FingerTable {
+    let own_id = hash(own_address.as_bytes());
+    let mut finger_table = FingerTable::new(&own_id);
+
+    match successor_client.get_finger_table(Request::new(Empty {})).await {
+        Ok(response) => {
+            let mut known_addresses: Vec<Address> = response.into_inner().fingers.into_iter()
+                .map(|finger| finger.address)
+                .filter(|address| !address.is_empty())
+                .collect();
+            known_addresses.push(successor_address.clone());
+            known_addresses.sort();
+            known_addresses.dedup();
+            finger_table.seed(&known_addresses);
+        }
+        Err(e) => warn!("Failed to copy finger table of successor {}: {}", successor_address, e)
+    }
+
+    let mut lookups = 0;
+    let mut last_address = successor_address.clone();
+    for finger in finger_table.fingers.iter_mut() {
+        if !is_between(finger.key, own_id + 1, hash(last_address.as_bytes()), false, false) {
+            lookups += 1;
+            match successor_client.find_successor(Request::new(finger.key.into())).await {
+                Ok(response) => {
+                    let responsible_address: Address = response.into_inner().into();
+                    // this node is not part of the ring yet, so the lookup skips it
+                    last_address = if is_between(own_id, finger.key, hash(responsible_address.as_bytes()), false, true) {
+                        own_address.clone()
+                    } else {
+                        responsible_address
+                    };
+                }
+                Err(e) => {
+                    warn!("Failed to look up finger {}, keeping {}: {}", finger.key, finger.address, e);
+                    continue;
+                }
+            }
+        }
+        finger.address = last_address.clone();
+    }
+    info!("Bootstrapped {} fingers with {} lookups", finger_table.fingers.len(), lookups);
+    finger_table
+}
+
+/// wraps the storage to enforce the quota, if one is configured
+fn with_quota<S: Storage + 'static>(storage: S, quota: StorageQuota, quota_stats_arc: Arc<Mutex<QuotaStats>>) -> Arc<Mutex<KvStore>> {
+    if quota.is_limited() {
+        info!("Limiting storage to {:?} bytes and {:?} entries with eviction policy {:?}", quota.max_bytes, quota.max_entries, quota.eviction_policy);
+        Arc::new(Mutex::new(QuotaStorage::new(Box::new(storage), quota, quota_stats_arc)))
+    } else {
+        Arc::new(Mutex::new(storage))
+    }
+}
@@ -1,29 +1,24 @@
 use std::error::Error;
 use std::sync::{Arc, Mutex};
 
-use log::{error, info};
+use log::{error, info, warn};
 use tokio::signal;
 use tokio::sync::oneshot::Receiver;
-use tokio_stream::iter;
 use tonic::Request;
 
-use chord::utils::crypto::{hash, is_between};
-use chord::utils::types::KvStore;
-
+use crate::node::storage::KvStore;
 use crate::node::successor_list::SuccessorList;
-use crate::threads::chord::{connect_to_first_reachable_node, connect_with_retry};
-use crate::threads::chord::chord_proto::{Empty, KvPairMsg};
-use crate::utils::crypto::HashRingKey;
-use crate::utils::types::{Address, HashPos};
-
-pub async fn shutdown_handoff(local_grpc_service_address: Address, rx: Receiver<Arc<Mutex<KvStore>>>) -> Result<(), Box<dyn Error>> {
+use crate::threads::chord::{connect_to_local_service, hand_off_and_leave};
+use crate::threads::chord::chord_proto::Empty;
+use crate::utils::types::Address;
+
+/// hands off all pairs to the first reachable successor once the process is asked to shut down
+/// and then leaves the ring. The addresses of the other virtual nodes of this process are skipped,
+/// as they shut down as well.
+pub async fn shutdown_handoff(local_grpc_service_address: Address, virtual_node_addresses: Vec<Address>, rx: Receiver<Arc<Mutex<KvStore>>>) -> Result<(), Box<dyn Error>> {
     let kv_store_arc = rx.await.unwrap();
-    let one = HashPos::one();
-
 
-    let mut local_grpc_client = connect_with_retry(&local_grpc_service_address)
-        .await
-        .unwrap();
+    let mut local_grpc_client = connect_to_local_service(&local_grpc_service_address).await;
     info!("Shutdown handoff thread ready...");
     match signal::ctrl_c().await {
         Ok(()) => {
@@ -31,30 +26,15 @@ pub async fn shutdown_handoff(local_grpc_service_address: Address, rx: Receiver<
             let successor_list: SuccessorList = local_grpc_client.get_successor_list(Request::new(Empty {}))
                 .await
                 .unwrap().into_inner().into();
-
-            let (mut successor_client, successor_address) = connect_to_first_reachable_node(&successor_list.successors)
+            let predecessor_option: Option<Address> = local_grpc_client.get_predecessor(Request::new(Empty {}))
                 .await
-                .unwrap();
-            info!("Selected successor for handoff");
-
-            let mut counter = 0;
-            let foo: Vec<KvPairMsg> = {
-                let bar = kv_store_arc.lock().unwrap();
-                bar.iter()
-                    .filter(move |(key, _)| is_between(hash(*key), one + 1, one, false, false))
-                    .inspect(|_| { counter += 1; })
-                    .map(|(k, (v, expiration_date))| {
-                        KvPairMsg {
-                            key: k.to_vec(),
-                            value: v.to_string(),
-                            expiration_date: expiration_date.clone()
-                        }
-                    })
-                    .collect()
-            };
+                .unwrap().into_inner().address_optional
+                .map(|address| address.into())
+                .filter(|address: &Address| !address.is_empty());
 
-            let _ = successor_client.handoff(Request::new(iter(foo))).await;
-            info!("Transfered {} key-value-pairs to {}", counter, successor_address);
+            if hand_off_and_leave(&local_grpc_service_address, &kv_store_arc, successor_list, predecessor_option, &virtual_node_addresses).await.is_none() {
+                warn!("No other node reachable, shutting down without handoff");
+            }
         }
         Err(err) => {
             error!("Unable to listen for shutdown signal: {}", err);
@@ -62,3 +42,4 @@ pub async fn shutdown_handoff(local_grpc_service_address: Address, rx: Receiver<
     }
     Ok(())
 }
+
@@ -0,0 +1,28 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, error, info};
+use tokio::sync::oneshot::Receiver;
+use tokio::time::sleep;
+
+use crate::node::storage::KvStore;
+use crate::utils::constants::SNAPSHOT_SLEEP_MILLIS;
+
+/// periodically writes a snapshot of the key value storage and truncates its write-ahead log, so
+/// that the log does not grow indefinitely. Returns immediately if the node runs without a data
+/// directory.
+pub async fn snapshot_periodically(rx: Receiver<Arc<Mutex<KvStore>>>) -> () {
+    let kv_store_arc = rx.await.unwrap();
+    if !kv_store_arc.lock().unwrap().is_persistent() {
+        debug!("No data directory configured, snapshot thread not needed");
+        return;
+    }
+    info!("Starting up periodic snapshot thread");
+    loop {
+        sleep(Duration::from_millis(SNAPSHOT_SLEEP_MILLIS)).await;
+        match kv_store_arc.lock().unwrap().snapshot() {
+            Ok(_) => debug!("Wrote snapshot of key value storage"),
+            Err(err) => error!("Failed to write snapshot: {}", err)
+        }
+    }
+}
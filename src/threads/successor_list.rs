@@ -6,36 +6,61 @@ use tokio::sync::oneshot::Receiver;
 use tokio::time::sleep;
 use tonic::Request;
 use chord::utils::constants::HEALTH_SLEEP_MILLIS;
-use crate::node::successor_list::{SUCCESSOR_LIST_SIZE, SuccessorList};
-use crate::threads::chord::connect_with_retry;
+use crate::node::failure_detector::failure_detector;
+use crate::node::lookup_cache::lookup_cache;
+use crate::node::successor_list::SuccessorList;
+use crate::threads::chord::{connect_to_local_service, connect_with_retry, evict_if_unavailable, probe_suspected};
 use crate::threads::chord::chord_proto::Empty;
 
 /// periodic successor list checking:
 /// this function fetches the successor's successor list and updates this node's successor list
-/// with the successor's successor list
-pub async fn check_successor_list_periodically(local_grpc_service_address: String, rx: Receiver<Arc<Mutex<SuccessorList>>>) -> ! {
+/// with the successor's successor list. If the list has changed, the replicas of this node's data
+/// are re-created on the new successors.
+pub async fn check_successor_list_periodically(local_grpc_service_address: String, successor_list_size: usize, rx: Receiver<Arc<Mutex<SuccessorList>>>) -> ! {
     let successor_list_arc = rx.await.unwrap();
     info!("Starting up periodic successor list check thread");
 
-    let mut local_grpc_client = connect_with_retry(&local_grpc_service_address)
-        .await
-        .unwrap();
+    let mut local_grpc_client = connect_to_local_service(&local_grpc_service_address).await;
 
     loop {
         let successor_list: SuccessorList = local_grpc_client.get_successor_list(Request::new(Empty {}))
             .await
             .unwrap().into_inner().into();
 
-        for i in 0..SUCCESSOR_LIST_SIZE {
-            match connect_with_retry(&successor_list.successors[i]).await {
+        for (i, successor) in successor_list.successors.iter().enumerate() {
+            match connect_with_retry(successor).await {
                 Ok(mut successor_client) => {
-                    let successors_successor_list: SuccessorList = successor_client.get_successor_list(Request::new(Empty{}))
-                        .await
-                        .unwrap().into_inner().into();
-                    successor_list_arc.lock().unwrap().update_with_other_succ_list(successors_successor_list.clone());
+                    let mut successors_successor_list: SuccessorList = match successor_client.get_successor_list(Request::new(Empty{})).await {
+                        Ok(response) => {
+                            failure_detector().lock().unwrap().heartbeat(successor);
+                            response.into_inner().into()
+                        }
+                        Err(status) => {
+                            failure_detector().lock().unwrap().missed_heartbeat(successor);
+                            evict_if_unavailable(successor, &status);
+                            warn!("{}-th successor did not respond, retrying with next...", i+1);
+                            continue;
+                        }
+                    };
+                    probe_suspected(&successors_successor_list.successors).await;
+                    failure_detector().lock().unwrap().retain_available(&mut successors_successor_list.successors);
+                    let has_changed = {
+                        let mut successor_list_guard = successor_list_arc.lock().unwrap();
+                        let previous_successors = successor_list_guard.successors.clone();
+                        successor_list_guard.update_with_other_succ_list(successors_successor_list.clone(), successor_list_size);
+                        previous_successors.ne(&successor_list_guard.successors)
+                    };
+                    if has_changed {
+                        info!("Successor list has changed, refreshing replicas");
+                        lookup_cache().lock().unwrap().clear();
+                        if let Err(e) = local_grpc_client.refresh_replicas(Request::new(Empty {})).await {
+                            warn!("Failed to refresh replicas: {}", e);
+                        }
+                    }
                     break;
                 },
                 Err(_) => {
+                    failure_detector().lock().unwrap().missed_heartbeat(successor);
                     warn!("Cannot connect to {}-th successor, retrying with next...", i+1)
                 }
             }
@@ -2,7 +2,9 @@ use std::sync::{Arc, Mutex};
 
 use actix_web::{get, HttpResponse, Responder, web};
 use actix_web::web::Query;
-use serde::Deserialize;
+use base64::Engine;
+use base64::engine::general_purpose;
+use serde::{Deserialize, Serialize};
 use tera::{Context, Tera};
 use tonic::Request;
 
@@ -11,23 +13,48 @@ use chord::utils::crypto;
 use chord::utils::types::HashPos;
 
 use crate::node::finger_table::FingerTable;
-use crate::threads::chord::chord_proto::{GetRequest, GetStatus, PutRequest};
+use crate::node::metrics::LookupStats;
+use crate::node::quota_storage::QuotaStats;
+use crate::threads::chord::chord_proto::{Empty, GetRequest, GetStatus, PutRequest, PutStatus, RemoveRequest, RemoveStatus};
 use crate::threads::chord::connect_with_retry;
-use crate::threads::client_api::perform_chord_look_up;
+use crate::threads::client_api::{call_responsible_node, perform_chord_look_up_with_path};
+use crate::threads::expiration::SweepStats;
 
 #[derive(Deserialize)]
 struct QueryParams {
     get_request_key: Option<String>,
     put_request_key: Option<String>,
     put_request_value: Option<String>,
+    put_request_expected_version: Option<String>,
+    remove_request_key: Option<String>,
+    maintenance_request: Option<String>,
+}
+
+/// Finger entry as shown on the web interface. The positions are formatted in advance, as the
+/// template engine can not handle 128 bit integers.
+#[derive(Serialize)]
+struct FingerView {
+    address: String,
+    key: String,
+    relative_key: f64,
+}
+
+/// Finger table of one virtual node of this process
+#[derive(Serialize)]
+struct FingerTableView {
+    address: String,
+    fingers: Vec<FingerView>,
 }
 
 
 #[get("/")]
 pub async fn index(
-    finger_table_data: web::Data<Arc<Mutex<FingerTable>>>,
+    finger_tables_data: web::Data<Vec<Arc<Mutex<FingerTable>>>>,
     config: web::Data<Config>,
     local_grpc_address: web::Data<String>,
+    sweep_stats: web::Data<Arc<Mutex<SweepStats>>>,
+    quota_stats: web::Data<Arc<Mutex<QuotaStats>>>,
+    lookup_stats: web::Data<Arc<Mutex<LookupStats>>>,
     query_params_option: Option<Query<QueryParams>>,
 ) -> impl Responder {
     let tera = Tera::new("static/html/**/*").unwrap();
@@ -38,7 +65,10 @@ pub async fn index(
             QueryParams {
                 get_request_key: Some(get_input),
                 put_request_key: None,
-                put_request_value: None
+                put_request_value: None,
+                put_request_expected_version: None,
+                remove_request_key: None,
+                maintenance_request: None
             } => {
                 perform_get_and_update_context(&get_input, &local_grpc_address, &mut context)
                     .await;
@@ -46,23 +76,84 @@ pub async fn index(
             QueryParams {
                 get_request_key: None,
                 put_request_key: Some(put_key_input),
-                put_request_value: Some(put_value_input)
+                put_request_value: Some(put_value_input),
+                put_request_expected_version: expected_version_input_option,
+                remove_request_key: None,
+                maintenance_request: None
             } => {
-                perform_put_and_update_context(&put_key_input, put_value_input, &local_grpc_address, &mut context)
+                // an empty expected version field means an unconditional put
+                let expected_version_option = match expected_version_input_option
+                    .filter(|input| !input.is_empty())
+                    .map(|input| input.parse::<u64>())
+                    .transpose() {
+                    Ok(expected_version_option) => expected_version_option,
+                    Err(_) => return HttpResponse::BadRequest()
+                        .content_type("text/plain")
+                        .body("Expected version must be a non-negative number"),
+                };
+                perform_put_and_update_context(&put_key_input, put_value_input, expected_version_option, &local_grpc_address, &mut context)
                     .await;
             }
-            QueryParams { get_request_key: None, put_request_key: None, put_request_value: None } => {}
+            QueryParams {
+                get_request_key: None,
+                put_request_key: None,
+                put_request_value: None,
+                put_request_expected_version: None,
+                remove_request_key: Some(remove_input),
+                maintenance_request: None
+            } => {
+                perform_remove_and_update_context(&remove_input, &local_grpc_address, &mut context)
+                    .await;
+            }
+            QueryParams {
+                get_request_key: None,
+                put_request_key: None,
+                put_request_value: None,
+                put_request_expected_version: None,
+                remove_request_key: None,
+                maintenance_request: Some(maintenance_input)
+            } => {
+                // draining takes the node out of the ring, so it is only offered in dev mode
+                if !config.dev_mode {
+                    return HttpResponse::Forbidden()
+                        .content_type("text/plain")
+                        .body("Maintenance requests require dev_mode");
+                }
+                perform_maintenance_and_update_context(&maintenance_input, &local_grpc_address, &mut context)
+                    .await;
+            }
+            QueryParams {
+                get_request_key: None,
+                put_request_key: None,
+                put_request_value: None,
+                put_request_expected_version: None,
+                remove_request_key: None,
+                maintenance_request: None
+            } => {}
             _ => { panic!("Invalid query params") }
         }
     }
 
     context.insert("title", "Chord Node");
 
-    let finger_table_guard = finger_table_data.lock().unwrap();
-
     context.insert("config", &config);
-    context.insert("fingers", &finger_table_guard.fingers);
-    context.insert("max_pos", &HashPos::MAX);
+    let finger_tables: Vec<FingerTableView> = config.virtual_node_addresses().into_iter()
+        .zip(finger_tables_data.iter())
+        .map(|(address, finger_table_arc)| FingerTableView {
+            address,
+            fingers: finger_table_arc.lock().unwrap().fingers.iter()
+                .map(|finger| FingerView {
+                    address: finger.get_address().clone(),
+                    key: finger.get_key().to_string(),
+                    relative_key: *finger.get_key() as f64 / HashPos::MAX as f64 * 100.0,
+                })
+                .collect(),
+        })
+        .collect();
+    context.insert("finger_tables", &finger_tables);
+    context.insert("sweep_stats", &sweep_stats.lock().unwrap().clone());
+    context.insert("quota_stats", &quota_stats.lock().unwrap().clone());
+    context.insert("lookup_stats", &lookup_stats.lock().unwrap().clone());
 
     let rendered_html = tera.render("index.html", &context).unwrap();
 
@@ -78,17 +169,33 @@ async fn perform_get_and_update_context(key: &String, local_grpc_address: &Strin
     }
 
     let hash_ring_pos: HashPos = crypto::hash(key_array.as_slice());
-    let mut responsible_node_client = perform_chord_look_up(&hash_ring_pos, local_grpc_address.as_str())
+    let (mut responsible_node_client, lookup_response) = perform_chord_look_up_with_path(&hash_ring_pos, local_grpc_address.as_str())
         .await;
 
+    let lookup_path: Vec<String> = lookup_response.hops.iter()
+        .map(|hop| format!("{} ({} us)", hop.address, hop.latency_micros))
+        .collect();
+    context.insert("lookup_path", &lookup_path);
+    context.insert("lookup_hop_count", &lookup_response.hops.len().saturating_sub(1));
+
     let response = responsible_node_client.get(Request::new(GetRequest {
         key: key_array.to_vec(),
     })).await.unwrap();
 
+    if let Some(replica_status) = &response.get_ref().replica_status {
+        context.insert("replica_status", &format!("{} of {} replicas responded, {} repaired",
+                                                  replica_status.responded, replica_status.read_quorum, replica_status.repaired));
+        context.insert("degraded_read", &(replica_status.responded < replica_status.read_quorum));
+    }
+
     match GetStatus::from_i32(response.get_ref().status) {
         Some(GetStatus::Ok) => {
             context.insert("response_status", "OK");
-            context.insert("get_response", &response.get_ref().value);
+            context.insert("get_response_version", &response.get_ref().version);
+            let value = &response.get_ref().value;
+            context.insert("get_response", &String::from_utf8_lossy(value));
+            context.insert("get_response_hex", &value.iter().map(|byte| format!("{:02x}", byte)).collect::<String>());
+            context.insert("get_response_base64", &general_purpose::STANDARD.encode(value));
         }
         Some(GetStatus::NotFound) => {
             context.insert("response_status", "NOT_FOUND");
@@ -100,20 +207,65 @@ async fn perform_get_and_update_context(key: &String, local_grpc_address: &Strin
     }
 }
 
-async fn perform_put_and_update_context(key: &String, value: String, local_grpc_address: &String, context: &mut Context) {
+async fn perform_put_and_update_context(key: &String, value: String, expected_version_option: Option<u64>, local_grpc_address: &String, context: &mut Context) {
     let mut key_array: [u8; 32] = [0; 32];
     for (i, c) in key.chars().enumerate() {
         key_array[i] = c as u8;
     }
 
     let hash_ring_pos: HashPos = crypto::hash(key_array.as_slice());
-    let mut responsible_node_client = perform_chord_look_up(&hash_ring_pos, local_grpc_address.as_str())
-        .await;
+    let response = call_responsible_node(&hash_ring_pos, local_grpc_address.as_str(), |mut client| {
+        let value = value.clone();
+        async move {
+            client.put(Request::new(PutRequest {
+                key: key_array.to_vec(),
+                ttl: 100000,
+                replication: 0,
+                value: value.into_bytes(),
+                expected_version: expected_version_option,
+            })).await
+        }
+    }).await.unwrap();
 
-    let _ = responsible_node_client.put(Request::new(PutRequest {
-        key: key_array.to_vec(),
-        ttl: 100000,
-        replication: 0,
-        value,
-    })).await.unwrap();
+    match PutStatus::from_i32(response.get_ref().status) {
+        Some(PutStatus::Ok) => context.insert("put_response_status", "OK"),
+        Some(PutStatus::VersionMismatch) => context.insert("put_response_status", "VERSION_MISMATCH"),
+        Some(PutStatus::QuotaExceeded) => context.insert("put_response_status", "QUOTA_EXCEEDED"),
+        _ => panic!("Received invalid put response status")
+    }
+    context.insert("put_response_version", &response.get_ref().version);
+}
+
+async fn perform_remove_and_update_context(key: &str, local_grpc_address: &str, context: &mut Context) {
+    let mut key_array: [u8; 32] = [0; 32];
+    for (i, c) in key.chars().enumerate() {
+        key_array[i] = c as u8;
+    }
+
+    let hash_ring_pos: HashPos = crypto::hash(key_array.as_slice());
+    let response = call_responsible_node(&hash_ring_pos, local_grpc_address, |mut client| async move {
+        client.remove(Request::new(RemoveRequest {
+            key: key_array.to_vec(),
+        })).await
+    }).await.unwrap();
+
+    match RemoveStatus::from_i32(response.get_ref().status) {
+        Some(RemoveStatus::Ok) => context.insert("remove_response_status", "OK"),
+        Some(RemoveStatus::NotFound) => context.insert("remove_response_status", "NOT_FOUND"),
+        _ => panic!("Received invalid remove response status")
+    }
+}
+
+/// drains the local node or lets it re-join the ring
+async fn perform_maintenance_and_update_context(action: &str, local_grpc_address: &String, context: &mut Context) {
+    let mut client = connect_with_retry(local_grpc_address).await.unwrap();
+    let result = match action {
+        "drain" => client.drain(Request::new(Empty {})).await,
+        "undrain" => client.undrain(Request::new(Empty {})).await,
+        _ => panic!("Invalid maintenance request")
+    };
+    match result {
+        Ok(_) => context.insert("maintenance_response_status", "OK"),
+        Err(status) => context.insert("maintenance_response_status", status.message())
+    }
 }
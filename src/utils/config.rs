@@ -1,3 +1,4 @@
+use std::net::SocketAddr;
 use std::str::FromStr;
 
 use clap::Parser;
@@ -5,7 +6,7 @@ use ini::{Error, Ini};
 use log::LevelFilter;
 use serde::Serialize;
 
-use crate::utils::constants::POW_DIFFICULTY_DEFAULT;
+use crate::utils::constants::{POW_DIFFICULTY_DEFAULT, READ_QUORUM_DEFAULT, SUCCESSOR_LIST_SIZE_DEFAULT, VIRTUAL_NODE_PORT_OFFSET, WEIGHT_DEFAULT};
 use crate::utils::types::Address;
 
 /// The config struct is initialized from a config file upon node start up
@@ -18,16 +19,141 @@ pub struct Cli {
 }
 
 
+/// What a node does when a put would exceed its storage quota
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionPolicy {
+    /// the put is rejected
+    #[default]
+    Reject,
+    /// the least recently used pairs are evicted
+    Lru,
+    /// the pairs expiring first are evicted
+    EarliestExpiry,
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(EvictionPolicy::Reject),
+            "lru" => Ok(EvictionPolicy::Lru),
+            "earliest_expiry" => Ok(EvictionPolicy::EarliestExpiry),
+            _ => Err(format!("Invalid eviction policy '{}', use reject, lru or earliest_expiry", s))
+        }
+    }
+}
+
+/// How the node resolves the responsible node for a position, if the request does not choose
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LookupMode {
+    /// find_successor is forwarded from node to node
+    #[default]
+    Recursive,
+    /// the node queries every hop itself and records the path
+    Iterative,
+}
+
+impl FromStr for LookupMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "recursive" => Ok(LookupMode::Recursive),
+            "iterative" => Ok(LookupMode::Iterative),
+            _ => Err(format!("Invalid lookup mode '{}', use recursive or iterative", s))
+        }
+    }
+}
+
+/// Order in which the bootstrap peers are tried
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootstrapOrder {
+    /// the peers are tried in the configured order
+    #[default]
+    InOrder,
+    /// the peers are shuffled before every round
+    Random,
+}
+
+impl FromStr for BootstrapOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "in_order" => Ok(BootstrapOrder::InOrder),
+            "random" => Ok(BootstrapOrder::Random),
+            _ => Err(format!("Invalid bootstrap order '{}', use in_order or random", s))
+        }
+    }
+}
+
+/// What a node does if none of its bootstrap peers is reachable
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootstrapFallback {
+    /// the peers are retried until one of them answers
+    #[default]
+    Retry,
+    /// the node starts a new ring after a few rounds
+    NewRing,
+}
+
+impl FromStr for BootstrapFallback {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "retry" => Ok(BootstrapFallback::Retry),
+            "new_ring" => Ok(BootstrapFallback::NewRing),
+            _ => Err(format!("Invalid bootstrap fallback '{}', use retry or new_ring", s))
+        }
+    }
+}
+
+/// Peers through which a node joins an existing ring, a new ring is started if there are none
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Bootstrap {
+    pub peers: Vec<Address>,
+    pub order: BootstrapOrder,
+    pub fallback: BootstrapFallback,
+}
+
+/// Limits of the node's storage, no limit is enforced if both are None
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct StorageQuota {
+    pub max_bytes: Option<u64>,
+    pub max_entries: Option<usize>,
+    pub eviction_policy: EvictionPolicy,
+}
+
+impl StorageQuota {
+    pub fn is_limited(&self) -> bool {
+        self.max_bytes.is_some() || self.max_entries.is_some()
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct Config {
     pub api_address: Address,
     pub p2p_address: Address,
     pub web_address: Address,
-    pub join_address: Option<Address>,
+    pub bootstrap: Bootstrap,
     pub pow_difficulty: usize,
     #[serde(skip_serializing)]
     pub log_level_filter: LevelFilter,
     pub dev_mode: bool,
+    pub read_quorum: u32,
+    pub data_dir: Option<String>,
+    pub quota: StorageQuota,
+    pub lookup_mode: LookupMode,
+    /// number of virtual nodes, i.e. ring positions, run by this process
+    pub weight: u32,
+    /// maximum number of successors every node keeps track of
+    pub successor_list_size: usize,
 }
 
 impl Config {
@@ -56,9 +182,26 @@ impl Config {
             .unwrap()
             .to_string();
 
-        let join_address = dht
+        // the single join address of older configs is tried before the bootstrap peers
+        let peers: Vec<Address> = dht
             .get("join_address")
-            .map(|join_address_str| join_address_str.to_string());
+            .into_iter()
+            .chain(dht.get("bootstrap_peers").into_iter().flat_map(|peers| peers.split(',')))
+            .map(|peer| peer.trim().to_string())
+            .filter(|peer| !peer.is_empty())
+            .collect();
+
+        let order = dht
+            .get("bootstrap_order")
+            .map(|order| BootstrapOrder::from_str(order).unwrap())
+            .unwrap_or_default();
+
+        let fallback = dht
+            .get("bootstrap_fallback")
+            .map(|fallback| BootstrapFallback::from_str(fallback).unwrap())
+            .unwrap_or_default();
+
+        let bootstrap = Bootstrap { peers, order, fallback };
 
         let pow_difficulty = dht
             .get("pow_difficulty")
@@ -77,6 +220,80 @@ impl Config {
             .map(|dev_mode| dev_mode.expect("Invalid dev mode argument, use true or false"))
             .unwrap_or(false);
 
-        Ok(Config { p2p_address, api_address, web_address, join_address, pow_difficulty, log_level_filter, dev_mode })
+        let read_quorum = dht
+            .get("read_quorum")
+            .map(|read_quorum| read_quorum.parse::<u32>().expect("Invalid read quorum"))
+            .unwrap_or(READ_QUORUM_DEFAULT);
+
+        let data_dir = dht
+            .get("data_dir")
+            .map(|data_dir| data_dir.to_string());
+
+        let max_bytes = dht
+            .get("max_bytes")
+            .map(|max_bytes| max_bytes.parse::<u64>().expect("Invalid max bytes"));
+
+        let max_entries = dht
+            .get("max_entries")
+            .map(|max_entries| max_entries.parse::<usize>().expect("Invalid max entries"));
+
+        let eviction_policy = dht
+            .get("eviction_policy")
+            .map(|eviction_policy| EvictionPolicy::from_str(eviction_policy).unwrap())
+            .unwrap_or_default();
+
+        let quota = StorageQuota { max_bytes, max_entries, eviction_policy };
+
+        let lookup_mode = dht
+            .get("lookup_mode")
+            .map(|lookup_mode| LookupMode::from_str(lookup_mode).unwrap())
+            .unwrap_or_default();
+
+        let weight = dht
+            .get("weight")
+            .map(|weight| weight.parse::<u32>().expect("Invalid weight"))
+            .unwrap_or(WEIGHT_DEFAULT);
+        assert!(weight > 0, "'weight' must be at least 1");
+        let p2p_port = p2p_address.parse::<SocketAddr>().expect("Invalid p2p address").port();
+        assert!(virtual_node_port(p2p_port, weight - 1).is_some(), "'weight' {} is too large, the ports of the virtual nodes would exceed {}", weight, u16::MAX);
+
+        let successor_list_size = dht
+            .get("successor_list_size")
+            .map(|successor_list_size| successor_list_size.parse::<usize>().expect("Invalid successor list size"))
+            .unwrap_or(SUCCESSOR_LIST_SIZE_DEFAULT);
+        assert!(successor_list_size > 0, "'successor_list_size' must be at least 1");
+
+        Ok(Config { p2p_address, api_address, web_address, bootstrap, pow_difficulty, log_level_filter, dev_mode, read_quorum, data_dir, quota, lookup_mode, weight, successor_list_size })
+    }
+
+    /// gRPC addresses of all virtual nodes, the first one is the p2p address. The i-th virtual node
+    /// listens on the p2p port plus i times VIRTUAL_NODE_PORT_OFFSET, so its ring position differs.
+    pub fn virtual_node_addresses(&self) -> Vec<Address> {
+        let p2p_socket_address: SocketAddr = self.p2p_address.parse().expect("Invalid p2p address");
+        (0..self.weight)
+            .map(|i| {
+                let mut socket_address = p2p_socket_address;
+                // the weight is checked against the port range when the config is loaded
+                socket_address.set_port(virtual_node_port(p2p_socket_address.port(), i).unwrap());
+                socket_address.to_string()
+            })
+            .collect()
+    }
+}
+
+/// port of the i-th virtual node, None if it exceeds the port range
+fn virtual_node_port(p2p_port: u16, index: u32) -> Option<u16> {
+    let offset = u16::try_from(index).ok()?.checked_mul(VIRTUAL_NODE_PORT_OFFSET)?;
+    p2p_port.checked_add(offset)
+}
+
+/// Whether both addresses belong to virtual nodes of the same process. The ports of the virtual
+/// nodes of a process differ by multiples of VIRTUAL_NODE_PORT_OFFSET, so nodes on the same host
+/// with the same port modulo the offset are assumed to share a process.
+pub fn is_same_process(address: &Address, other_address: &Address) -> bool {
+    match (address.parse::<SocketAddr>(), other_address.parse::<SocketAddr>()) {
+        (Ok(socket_address), Ok(other_socket_address)) => socket_address.ip() == other_socket_address.ip()
+            && socket_address.port() % VIRTUAL_NODE_PORT_OFFSET == other_socket_address.port() % VIRTUAL_NODE_PORT_OFFSET,
+        _ => address.eq(other_address)
     }
 }
@@ -1,13 +1,33 @@
 pub static FIX_FINGERS_SLEEP_MILLIS: u64 = 100;
 pub static STABILIZE_SLEEP_MILLIS: u64 = 1_000;
 pub static HEALTH_SLEEP_MILLIS: u64 = 1_000;
+pub static SNAPSHOT_SLEEP_MILLIS: u64 = 60_000;
+pub static SWEEP_SLEEP_MILLIS: u64 = 10_000;
+pub static CHANNEL_IDLE_TIMEOUT_MILLIS: u64 = 60_000;
+pub static TRANSFER_ACK_TIMEOUT_MILLIS: u64 = 5_000;
+pub static PHI_THRESHOLD: f64 = 8.0;
+pub static HEARTBEAT_WINDOW_SIZE: usize = 100;
+pub static MIN_HEARTBEAT_STD_DEV_MILLIS: u64 = 100;
+pub static BOOTSTRAP_BACKOFF_INITIAL_MILLIS: u64 = 500;
+pub static BOOTSTRAP_BACKOFF_MAX_MILLIS: u64 = 30_000;
+pub static BOOTSTRAP_ROUNDS: u32 = 3;
 pub static POW_DIFFICULTY_DEFAULT: usize = 2;
 pub static POW_TOKEN_LIVE_TIME: u64 = 5;
+pub static TOMBSTONE_LIVE_TIME: u64 = 600;
 pub static POW_THREAD_NUM: usize = 32;
+pub static READ_QUORUM_DEFAULT: u32 = 1;
+pub static WEIGHT_DEFAULT: u32 = 1;
+pub static SUCCESSOR_LIST_SIZE_DEFAULT: usize = 3;
+pub static VIRTUAL_NODE_PORT_OFFSET: u16 = 1000;
 
 pub static DHT_PUT: u16 = 650;
 pub static DHT_GET: u16 = 651;
 pub static DHT_SUCCESS: u16 = 652;
 pub static DHT_FAILURE: u16 = 653;
+pub static DHT_REMOVE: u16 = 654;
+pub static DHT_QUOTA_EXCEEDED: u16 = 655;
+pub static DHT_SUCCESS_DEGRADED: u16 = 656;
 
 pub static DEBUG_RPCS_UNAVAILABLE_ERROR_MESSAGE: &'static str = "Debug RPCs unavailable";
+pub static NOT_RESPONSIBLE_ERROR_MESSAGE: &str = "is responsible for range";
+pub static DRAINED_ERROR_MESSAGE: &str = "is drained";
@@ -7,7 +7,6 @@ use crate::utils::types::HashPos;
 pub trait HashRingKey {
     fn size() -> usize;
     fn one() -> HashPos;
-    fn two() -> HashPos;
     fn finger_count() -> usize;
 }
 
@@ -20,17 +19,14 @@ impl HashRingKey for HashPos {
         HashPos::default() + 1
     }
 
-    fn two() -> HashPos {
-        HashPos::default() + 2
-    }
-
     fn finger_count() -> usize {
         HashPos::size() * 8
     }
 }
 
 /// Central hashing function used to map addresses to hash ring locations
-/// It is also used for POW token validation
+/// It is also used for POW token validation. The position is taken from the first
+/// `HashPos::size()` bytes of the blake3 output.
 pub fn hash(input: &[u8]) -> HashPos {
     let mut hasher = Hasher::new();
     hasher.update(input);
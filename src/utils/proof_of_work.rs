@@ -12,7 +12,7 @@ extern crate rayon;
 pub struct PowToken {
     pub timestamp: u64,
     pub nonce: u64,
-    pub(crate) pow_difficulty: usize
+    pub pow_difficulty: usize
 }
 
 impl fmt::Display for PowToken {
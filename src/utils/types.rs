@@ -1,13 +1,31 @@
-use std::collections::HashMap;
-
-// todo: change to u128
-pub type HashPos = u64;
+/// position on the hash ring, the finger table has one entry per bit
+pub type HashPos = u128;
 
 pub type ExpirationDate = u64;
 
+pub type Replication = u32;
+
+/// milliseconds since the epoch at which a value was written, used to find the freshest replica
+pub type Timestamp = u64;
+/// version of a value, 0 if the key does not exist
+pub type Version = u64;
+
 pub type Key = [u8; 32];
-pub type Value = String;
+pub type Value = Vec<u8>;
 
 pub type Address = String;
-pub type KvStore = HashMap<Key, (Value, u64)>;
+
+/// A value stored in the KvStore together with its metadata
+#[derive(Clone, Debug, Default)]
+pub struct KvEntry {
+    pub value: Value,
+    pub expiration_date: ExpirationDate,
+    pub replication: Replication,
+    pub timestamp: Timestamp,
+    /// marks a removed key, the entry is kept until it expires so that replicas cannot bring back
+    /// the removed value
+    pub tombstone: bool,
+    /// incremented on every put and remove of the key, used for compare-and-set puts
+    pub version: Version,
+}
 
@@ -16,6 +16,7 @@ DHT_PUT = 650
 DHT_GET = 651
 DHT_SUCCESS = 652
 DHT_FAILURE = 653
+DHT_SUCCESS_DEGRADED = 656
 
 HOST_DISCONNECTS = False
 
@@ -41,6 +42,10 @@ def send_get(s, dht_key):
             avalue = buf[int(256/8)+4:]
             print(f"[+] Received DHT_SUCCESS."
                   + f" size: {asize}, key: {akey}, value: {avalue}")
+        elif atype == DHT_SUCCESS_DEGRADED:
+            avalue = buf[int(256/8)+4:]
+            print(f"[+] Received DHT_SUCCESS_DEGRADED, not all replicas responded."
+                  + f" size: {asize}, key: {akey}, value: {avalue}")
         elif atype == DHT_FAILURE:
             print(f"[+] Received DHT_FAILURE."
                   + f" size: {asize}, key: {akey}")